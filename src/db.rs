@@ -1,17 +1,26 @@
 //! A simple database engine that can read a database file, parse queries, and return results. It contains struct to represent the database, its metadata, and responses.
 //!
-//! This module has two submodules:
+//! This module has three submodules:
 //! * [fileformat] contains what we need to parser the sqlite file
 //! * [dbmetadata] contains all the information on the sqlite database
+//! * [cursor] contains the cursors used to walk B-trees
 //!
+use crate::db::cursor::TableCursor;
 use crate::db::dbmetadata::DBMetadata;
-use crate::db::fileformat::page::Page;
+use crate::db::fileformat::{
+    cell::Cell,
+    header::{DbHeader, HEADER_SIZE, TextEncoding},
+    page::Page,
+};
 use crate::db::table::Table;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::{Read, Seek, SeekFrom};
+use std::rc::Rc;
 
+pub mod cursor;
 pub mod dbmetadata;
 pub mod fileformat;
 pub mod table;
@@ -19,7 +28,11 @@ pub mod table;
 pub struct DB {
     pub metadata: DBMetadata,
     pub page_size: usize,
-    pub buf_reader: BufReader<File>,
+    // Page size minus the bytes reserved at the end of each page
+    pub usable_size: usize,
+    pub text_encoding: TextEncoding,
+    // Behind a RefCell so several cursors can read pages at the same time
+    buf_reader: RefCell<BufReader<File>>,
 }
 
 impl DB {
@@ -27,48 +40,128 @@ impl DB {
         let file = File::open(filename)?;
         let mut buf_reader = BufReader::new(file);
         // We need page_size to read pages. The page_size is defined in the database header.
-        let page_size = DB::get_page_size(&mut buf_reader)? as usize;
-
-        // We read the first page to build the metadata
-        let mut buffer = Vec::new();
-        buffer.resize(page_size as usize, 0);
-        buf_reader.read_exact(&mut buffer)?;
-        let page = Page::new(buffer, 1)?;
-        let metadata = DBMetadata::new(page)?;
+        let mut header = [0; HEADER_SIZE];
+        buf_reader.read_exact(&mut header)?;
+        buf_reader.rewind()?;
+        let header = DbHeader::new(&header)?;
+        let page_size = header.page_size;
+        let usable_size = header.usable_size();
+        let text_encoding = header.text_encoding;
 
-        Ok(Self {
-            metadata,
+        let mut db = Self {
+            metadata: DBMetadata::new(header),
             page_size,
-            buf_reader,
-        })
+            usable_size,
+            text_encoding,
+            buf_reader: RefCell::new(buf_reader),
+        };
+        db.load_schema()?;
+        Ok(db)
     }
 
-    fn get_page_size(buf_reader: &mut BufReader<File>) -> Result<u16> {
-        let mut header: [u8; 100] = [0; 100];
-        buf_reader.read_exact(&mut header)?;
-        buf_reader.rewind()?;
-        Ok(u16::from_be_bytes([header[16], header[17]]))
+    /// Walk the sqlite_schema table to build the metadata.
+    /// The schema is a table B-tree rooted at page 1, it can span several pages.
+    fn load_schema(&mut self) -> Result<()> {
+        let schema_table = Table::schema_table();
+        let mut cursor = TableCursor::new(self, schema_table.get_root_page())?;
+        let mut records = vec![];
+        while let Some(record) = cursor.next_record(&schema_table)? {
+            records.push(record);
+        }
+        for record in records {
+            self.metadata.add_schema_record(record)?;
+        }
+        Ok(())
     }
 
-    pub fn take_table(&mut self, tablename: &str) -> Option<Table> {
-        self.metadata.take_table(tablename)
+    pub fn get_table(&self, tablename: &str) -> Option<Rc<Table>> {
+        self.metadata.get_table(tablename)
     }
 
-    pub fn get_page(&mut self, root_page: usize) -> Result<Page> {
+    pub fn get_page(&self, root_page: usize) -> Result<Page> {
+        let page_buffer = self.read_page_buffer(root_page)?;
+        Page::new(page_buffer, root_page)
+    }
+
+    /// Read a page without parsing it. Used for pages that are not B-tree pages
+    /// like overflow pages.
+    fn read_page_buffer(&self, page_number: usize) -> Result<Vec<u8>> {
+        if page_number == 0 {
+            return Err(anyhow!("Page number 0 does not exist"));
+        }
         let mut page_buffer = self.get_new_page_buffer();
         // Page are numbered from 1, we need to subtract 1 to get the offset
-        let offset = ((root_page - 1) * self.page_size) as u64;
-        self.buf_reader.seek(SeekFrom::Start(offset))?;
-        self.buf_reader.read_exact(&mut page_buffer)?;
-        Page::new(page_buffer, root_page)
+        let offset = ((page_number - 1) * self.page_size) as u64;
+        let mut buf_reader = self.buf_reader.borrow_mut();
+        buf_reader.seek(SeekFrom::Start(offset))?;
+        buf_reader.read_exact(&mut page_buffer)?;
+        Ok(page_buffer)
+    }
+
+    /// Get the complete payload of a cell, following the overflow pages chain.
+    /// Each overflow page starts with the next overflow page number (0 for the last one)
+    /// followed by usable_size - 4 bytes of payload.
+    pub fn read_payload(&self, cell: &Cell) -> Result<Vec<u8>> {
+        let mut payload = Vec::with_capacity(cell.payload_size);
+        payload.extend_from_slice(cell.payload);
+        let mut next_page = cell.overflow_page;
+        while let Some(page_number) = next_page {
+            let buffer = self.read_page_buffer(page_number)?;
+            let remaining = cell.payload_size - payload.len();
+            let content_size = remaining.min(self.usable_size - 4);
+            payload.extend_from_slice(&buffer[4..4 + content_size]);
+            let next = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+            next_page = if next != 0 && payload.len() < cell.payload_size {
+                Some(next)
+            } else {
+                None
+            };
+        }
+        if payload.len() != cell.payload_size {
+            return Err(anyhow!(
+                "Overflow chain ended after {} bytes, expected {} bytes",
+                payload.len(),
+                cell.payload_size
+            ));
+        }
+        Ok(payload)
     }
 
     // Utility function that is used to provide a buffer
     // that can be used with read_exact or read.
     // Using with_capacity does not work.
     fn get_new_page_buffer(&self) -> Vec<u8> {
-        let mut buffer = Vec::new();
-        buffer.resize(self.page_size, 0);
-        buffer
+        vec![0; self.page_size]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_schema_db() -> DB {
+        DB::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/schema.db"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn it_should_load_a_schema_spanning_several_pages() {
+        let db = open_schema_db();
+        assert_eq!(db.metadata.schema.len(), 41);
+        for i in 1..=40 {
+            let table = db.get_table(&format!("table_{}", i)).unwrap();
+            assert_eq!(table.cols_name, vec!["id", "name"]);
+        }
+    }
+
+    #[test]
+    fn it_should_load_a_definition_stored_in_overflow_pages() {
+        let db = open_schema_db();
+        let table = db.get_table("wide").unwrap();
+        assert_eq!(table.cols_name.len(), 60);
+        assert_eq!(table.get_column_name(59), "long_column_name_60");
     }
 }
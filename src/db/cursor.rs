@@ -0,0 +1,412 @@
+//! Cursors to walk the B-trees stored in the database file.
+//!
+//! A table B-tree is made of interior pages that only contain pointers to their
+//! children, and of leaf pages that contain the records. Each interior cell holds
+//! a left child pointer, and the page header holds the right most pointer.
+//! Visiting the children in that order gives the records in rowid order.
+//! The key of an interior cell is the largest rowid of its left child.
+//!
+//! An index B-tree stores entries in both interior and leaf pages. An entry is a
+//! record made of the indexed columns followed by the rowid. The entry of an interior
+//! cell comes after all the entries of its left child.
+//! WITHOUT ROWID tables are stored as index B-trees keyed by their primary key.
+//!
+//! See 1.6. B-tree Pages in [Sqlite fileformat doc](https://www.sqlite.org/fileformat.html)
+use crate::db::DB;
+use crate::db::fileformat::cell::Cell;
+use crate::db::fileformat::header::TextEncoding;
+use crate::db::fileformat::page::{BTreeType, Page};
+use crate::db::fileformat::record::Record;
+use crate::db::table::Table;
+use crate::executor::db_response::RType;
+use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+
+/// Cursor over a table B-tree.
+///
+/// Pages are read lazily: only the pages on the path from the root to the
+/// current leaf are kept in memory.
+pub struct TableCursor<'a> {
+    db: &'a DB,
+    root_page: usize,
+    // Each entry is a page and the index of the next cell to visit in it.
+    // For interior pages, index == cell_number means the right most pointer.
+    stack: Vec<(Page, usize)>,
+}
+
+impl<'a> TableCursor<'a> {
+    pub fn new(db: &'a DB, root_page: usize) -> Result<Self> {
+        let root = db.get_page(root_page)?;
+        Ok(Self {
+            db,
+            root_page,
+            stack: vec![(root, 0)],
+        })
+    }
+
+    /// Move the cursor so the next record is the first one with a rowid >= `rowid`.
+    /// Each page on the way down is binary searched on the cell keys.
+    pub fn seek(&mut self, rowid: i64) -> Result<()> {
+        self.stack.clear();
+        let usable_size = self.db.usable_size;
+        let mut page = self.db.get_page(self.root_page)?;
+        loop {
+            let btree_type = &page.page_header.btree_type;
+            let cell_number = page.get_record_number();
+            let index = first_cell(cell_number, |i| {
+                let cell = Cell::new(page.get_cell(i)?, btree_type, usable_size)?;
+                Ok(cell.rowid.unwrap_or_default() >= rowid)
+            })?;
+            match btree_type {
+                BTreeType::LeafPage => {
+                    self.stack.push((page, index));
+                    return Ok(());
+                }
+                BTreeType::InteriorPage => {
+                    let child = if index < cell_number {
+                        page.get_left_child(index)?
+                    } else {
+                        page.page_header.right_most_pointer
+                    };
+                    self.stack.push((page, index + 1));
+                    page = self.db.get_page(child)?;
+                }
+                btree_type => {
+                    return Err(anyhow!(
+                        "Table cursor: page {} is not a table page: {:?}",
+                        page.page_number,
+                        btree_type
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns the record with the given rowid if it exists.
+    pub fn find<'t>(&mut self, rowid: i64, table: &'t Table) -> Result<Option<Record<'t>>> {
+        self.seek(rowid)?;
+        match self.next_record(table)? {
+            Some(record) if record.rowid as i64 == rowid => Ok(Some(record)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the next record of the table, or None when the whole tree was visited.
+    pub fn next_record<'t>(&mut self, table: &'t Table) -> Result<Option<Record<'t>>> {
+        loop {
+            let Some((page, index)) = self.stack.last_mut() else {
+                return Ok(None);
+            };
+            let cell_number = page.get_record_number();
+            let child = match page.page_header.btree_type {
+                BTreeType::LeafPage => {
+                    if *index < cell_number {
+                        let (rowid, payload) = read_cell_payload(self.db, page, *index)?;
+                        *index += 1;
+                        return Ok(Some(Record::new(
+                            &payload,
+                            rowid.unwrap_or_default(),
+                            table,
+                            self.db.text_encoding,
+                        )?));
+                    }
+                    None
+                }
+                BTreeType::InteriorPage => {
+                    let child = if *index < cell_number {
+                        Some(page.get_left_child(*index)?)
+                    } else if *index == cell_number {
+                        Some(page.page_header.right_most_pointer)
+                    } else {
+                        None
+                    };
+                    *index += 1;
+                    child
+                }
+                ref btree_type => {
+                    return Err(anyhow!(
+                        "Table cursor: page {} is not a table page: {:?}",
+                        page.page_number,
+                        btree_type
+                    ));
+                }
+            };
+
+            match child {
+                Some(page_number) => {
+                    let page = self.db.get_page(page_number)?;
+                    self.stack.push((page, 0));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Cursor over an index B-tree.
+///
+/// Entries are returned in the index order as the list of their values,
+/// the last value being the rowid of the indexed row.
+pub struct IndexCursor<'a> {
+    db: &'a DB,
+    root_page: usize,
+    // Each entry is a page and the next step to do in it.
+    // For interior pages with n cells, step 2i visits the left child of cell i
+    // (the right most pointer when i == n) and step 2i + 1 returns the entry of cell i.
+    stack: Vec<(Page, usize)>,
+}
+
+impl<'a> IndexCursor<'a> {
+    pub fn new(db: &'a DB, root_page: usize) -> Result<Self> {
+        let root = db.get_page(root_page)?;
+        Ok(Self {
+            db,
+            root_page,
+            stack: vec![(root, 0)],
+        })
+    }
+
+    /// Returns the next entry of the index, or None when the whole tree was visited.
+    pub fn next_entry(&mut self) -> Result<Option<Vec<RType>>> {
+        match self.next_payload()? {
+            Some(payload) => Ok(Some(Record::read_values(&payload, self.db.text_encoding)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the next row of a WITHOUT ROWID table, stored as an index B-tree.
+    pub fn next_record<'t>(&mut self, table: &'t Table) -> Result<Option<Record<'t>>> {
+        match self.next_payload()? {
+            Some(payload) => Ok(Some(Record::new(
+                &payload,
+                0,
+                table,
+                self.db.text_encoding,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    // Returns the payload of the next entry
+    fn next_payload(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            let Some((page, step)) = self.stack.last_mut() else {
+                return Ok(None);
+            };
+            let cell_number = page.get_record_number();
+            let child = match page.page_header.btree_type {
+                BTreeType::LeafIndex => {
+                    if *step < cell_number {
+                        let (_, payload) = read_cell_payload(self.db, page, *step)?;
+                        *step += 1;
+                        return Ok(Some(payload));
+                    }
+                    None
+                }
+                BTreeType::InteriorIndex => {
+                    let current = *step;
+                    let cell = current / 2;
+                    *step += 1;
+                    if current % 2 == 1 {
+                        if cell < cell_number {
+                            let (_, payload) = read_cell_payload(self.db, page, cell)?;
+                            return Ok(Some(payload));
+                        }
+                        None
+                    } else if cell < cell_number {
+                        Some(page.get_left_child(cell)?)
+                    } else if cell == cell_number {
+                        Some(page.page_header.right_most_pointer)
+                    } else {
+                        None
+                    }
+                }
+                ref btree_type => {
+                    return Err(anyhow!(
+                        "Index cursor: page {} is not an index page: {:?}",
+                        page.page_number,
+                        btree_type
+                    ));
+                }
+            };
+
+            match child {
+                Some(page_number) => {
+                    let page = self.db.get_page(page_number)?;
+                    self.stack.push((page, 0));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Move the cursor so the next entry is the first one whose leading values
+    /// are >= `key`.
+    pub fn seek(&mut self, key: &[RType]) -> Result<()> {
+        self.stack.clear();
+        let encoding = self.db.text_encoding;
+        let mut page = self.db.get_page(self.root_page)?;
+        loop {
+            let cell_number = page.get_record_number();
+            let index = first_cell(cell_number, |i| {
+                let (_, payload) = read_cell_payload(self.db, &page, i)?;
+                let entry = Record::read_values(&payload, self.db.text_encoding)?;
+                Ok(compare_key(&entry, key, encoding) != Ordering::Less)
+            })?;
+            match page.page_header.btree_type {
+                BTreeType::LeafIndex => {
+                    self.stack.push((page, index));
+                    return Ok(());
+                }
+                BTreeType::InteriorIndex => {
+                    let child = if index < cell_number {
+                        page.get_left_child(index)?
+                    } else {
+                        page.page_header.right_most_pointer
+                    };
+                    // Once the child is visited, the next step is the entry of the cell
+                    self.stack.push((page, 2 * index + 1));
+                    page = self.db.get_page(child)?;
+                }
+                ref btree_type => {
+                    return Err(anyhow!(
+                        "Index cursor: page {} is not an index page: {:?}",
+                        page.page_number,
+                        btree_type
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns the rowids of all the entries whose first value equals `value`.
+    pub fn find_rowids(&mut self, value: &RType) -> Result<Vec<i64>> {
+        let key = [value.clone()];
+        self.seek(&key)?;
+        let mut rowids = vec![];
+        while let Some(entry) = self.next_entry()? {
+            if compare_key(&entry, &key, self.db.text_encoding) != Ordering::Equal {
+                break;
+            }
+            if let Some(RType::Num(rowid)) = entry.last() {
+                rowids.push(*rowid);
+            }
+        }
+        Ok(rowids)
+    }
+}
+
+// Parse the nth cell of a page and read its complete payload.
+// Returns the cell rowid for table leaf cells.
+fn read_cell_payload(db: &DB, page: &Page, index: usize) -> Result<(Option<i64>, Vec<u8>)> {
+    let cell = Cell::new(
+        page.get_cell(index)?,
+        &page.page_header.btree_type,
+        db.usable_size,
+    )?;
+    let payload = db.read_payload(&cell)?;
+    Ok((cell.rowid, payload))
+}
+
+// Binary search the first cell for which `is_after` returns true.
+// Cells are sorted: `is_after` is false for the first cells and true for the others.
+fn first_cell(
+    cell_number: usize,
+    mut is_after: impl FnMut(usize) -> Result<bool>,
+) -> Result<usize> {
+    let (mut low, mut high) = (0, cell_number);
+    while low < high {
+        let middle = (low + high) / 2;
+        if is_after(middle)? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(low)
+}
+
+// Compare the leading values of an index entry with a key
+fn compare_key(entry: &[RType], key: &[RType], encoding: TextEncoding) -> Ordering {
+    entry
+        .iter()
+        .zip(key)
+        .map(|(left, right)| compare_values(left, right, encoding))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| entry.len().min(key.len()).cmp(&key.len()))
+}
+
+/// Index entries are sorted with the BINARY collation: text values are compared
+/// byte by byte in the database text encoding.
+pub fn compare_values(left: &RType, right: &RType, encoding: TextEncoding) -> Ordering {
+    match (left, right, encoding) {
+        (RType::Str(left), RType::Str(right), TextEncoding::Utf16le) => left
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .cmp(right.encode_utf16().flat_map(u16::to_le_bytes)),
+        (RType::Str(left), RType::Str(right), TextEncoding::Utf16be) => {
+            left.encode_utf16().cmp(right.encode_utf16())
+        }
+        _ => left.cmp(right),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_visit_all_records_in_rowid_order() {
+        let db = DB::new(concat!(env!("CARGO_MANIFEST_DIR"), "/sample.db")).unwrap();
+        let table = db.get_table("apples").unwrap();
+        let mut cursor = TableCursor::new(&db, table.get_root_page()).unwrap();
+        let mut rowids = vec![];
+        while let Some(record) = cursor.next_record(&table).unwrap() {
+            rowids.push(record.rowid);
+        }
+        assert_eq!(rowids, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn it_should_find_a_record_by_rowid() {
+        let db = DB::new(concat!(env!("CARGO_MANIFEST_DIR"), "/sample.db")).unwrap();
+        let table = db.get_table("oranges").unwrap();
+        let mut cursor = TableCursor::new(&db, table.get_root_page()).unwrap();
+        let record = cursor.find(3, &table).unwrap().unwrap();
+        assert_eq!(record.rowid, 3);
+        assert!(cursor.find(42, &table).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_should_binary_search_first_cell() {
+        let keys = [1, 3, 3, 7, 9];
+        let first = |key| first_cell(keys.len(), |i| Ok(keys[i] >= key)).unwrap();
+        assert_eq!(first(0), 0);
+        assert_eq!(first(3), 1);
+        assert_eq!(first(8), 4);
+        assert_eq!(first(10), 5);
+    }
+
+    #[test]
+    fn it_should_compare_text_keys_in_the_database_encoding() {
+        // U+0100 is 0x00 0x01 in UTF-16LE, so it sorts before 'b' (0x62 0x00)
+        let entry = [RType::Str("\u{100}".to_string()), RType::Num(1)];
+        let key = [RType::Str("b".to_string())];
+        assert_eq!(
+            compare_key(&entry, &key, TextEncoding::Utf8),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_key(&entry, &key, TextEncoding::Utf16be),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_key(&entry, &key, TextEncoding::Utf16le),
+            Ordering::Less
+        );
+    }
+}
@@ -1,99 +1,82 @@
 //! This module offer an abstraction over the sqlite database metadata
 //!
-use crate::db::fileformat::page::Page;
-use crate::db::table::{SchemaTable, Table};
+use crate::db::fileformat::{header::DbHeader, record::Record};
+use crate::db::table::{SchemaTable, Table, TableType};
 use crate::executor::db_response::{RType, Response};
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
+use std::rc::Rc;
 
 pub struct DBMetadata {
-    page: Page,
+    pub header: DbHeader,
     pub schema: SchemaTable,
 }
 
 impl DBMetadata {
-    pub fn new(page: Page) -> Result<Self> {
-        let schema = Self::create_table_schema(&page)?;
-        Ok(Self { page, schema })
-    }
-
-    fn create_table_schema(page: &Page) -> Result<SchemaTable> {
-        let mut schema: SchemaTable = HashMap::new();
-        let schema_table = Table::schema_table();
-        for n in 0..page.get_record_number() {
-            let mut record = page.get_nth_record(n, &schema_table)?;
-            let Some(RType::Str(table_type)) = record.take_field("table_type") else {
-                return Err(anyhow!("Wrong type table type schema"));
-            };
-            let Some(RType::Str(name)) = record.take_field("name") else {
-                return Err(anyhow!("Wrong type name schema"));
-            };
-            let Some(RType::Str(tablename)) = record.take_field("tablename") else {
-                return Err(anyhow!("Wrong type tablename schema"));
-            };
-            let rootpage = Self::get_root_page(record.take_field("rootpage"))?;
-            let Some(RType::Str(tabledef)) = record.take_field("tabledef") else {
-                return Err(anyhow!("Wrong type tabledef"));
-            };
-
-            let cols_name = Self::get_cols_name(&tabledef);
-
-            let table = Table::new(table_type, name, rootpage, tabledef, cols_name);
-            schema.insert(tablename.to_string(), table);
+    /// Create the metadata with an empty schema.
+    /// The schema is filled with [add_schema_record](DBMetadata::add_schema_record)
+    /// while walking the sqlite_schema table.
+    pub fn new(header: DbHeader) -> Self {
+        Self {
+            header,
+            schema: HashMap::new(),
         }
-        Ok(schema)
     }
 
-    fn get_cols_name(tabledef: &str) -> Vec<String> {
-        let values_str = tabledef.split('(').collect::<Vec<_>>()[1];
-        values_str
-            .split(',')
-            .map(|value| Self::trim_column_def(value.trim()))
-            .collect::<Vec<_>>()
-    }
+    /// Add an object of the sqlite_schema table to the schema
+    pub fn add_schema_record(&mut self, mut record: Record) -> Result<()> {
+        let Some(RType::Str(table_type)) = record.take_field("table_type") else {
+            return Err(anyhow!("Wrong type table type schema"));
+        };
+        let Some(RType::Str(name)) = record.take_field("name") else {
+            return Err(anyhow!("Wrong type name schema"));
+        };
+        let Some(RType::Str(tablename)) = record.take_field("tablename") else {
+            return Err(anyhow!("Wrong type tablename schema"));
+        };
+        let rootpage = Self::get_root_page(record.take_field("rootpage"))?;
+        // Indexes created for UNIQUE and PRIMARY KEY constraints have no sql
+        let tabledef = match record.take_field("tabledef") {
+            Some(RType::Str(tabledef)) => tabledef,
+            Some(RType::Null) => String::new(),
+            _ => return Err(anyhow!("Wrong type tabledef")),
+        };
 
-    fn trim_column_def(value: &str) -> String {
-        // NOTE: Why did I do that ?
-        if value.contains(' ') {
-            value
-                .split(' ')
-                .next()
-                .expect("We know that there is space")
-                .trim()
-                .to_string()
-        } else {
-            value.trim().to_string()
-        }
+        let table = Table::new(table_type, name, tablename, rootpage, tabledef)?;
+        // Names are case insensitive, the parser lowercases identifiers
+        self.schema
+            .insert(table.get_name().to_lowercase(), Rc::new(table));
+        Ok(())
     }
 
     fn get_root_page(record: Option<RType>) -> Result<usize> {
         match record {
-            Some(RType::Null) => {
-                return Err(anyhow!(
-                    "Table parsing: this type cannot be used for root_page"
-                ));
-            }
             Some(RType::Num(num)) => Ok(num as usize),
-            Some(RType::Blob(_)) => {
-                return Err(anyhow!(
-                    "Table parsing: this type cannot be used for root_page"
-                ));
-            }
-            Some(RType::Str(_)) => {
-                return Err(anyhow!(
-                    "Table parsing: this type cannot be used for root_page"
-                ));
-            }
-            None => {
-                return Err(anyhow!(
-                    "Table parsing: this type cannot be used for root_page"
-                ));
-            }
+            _ => Err(anyhow!(
+                "Table parsing: this type cannot be used for root_page"
+            )),
         }
     }
 
-    pub fn take_table(&mut self, tablename: &str) -> Option<Table> {
-        self.schema.remove(tablename)
+    pub fn get_table(&self, tablename: &str) -> Option<Rc<Table>> {
+        self.schema.get(tablename).cloned()
+    }
+
+    /// Find an index on `tablename` that can be searched on `column`: its first
+    /// column must be `column`, sorted in ascending order with the BINARY collation.
+    /// Partial indexes are skipped as they do not contain every row.
+    /// Returns the index root page.
+    pub fn find_index(&self, tablename: &str, column: &str) -> Option<usize> {
+        let table = self.get_table(&tablename.to_lowercase())?;
+        self.schema
+            .values()
+            .find(|index| {
+                index.get_table_type() == &TableType::Index
+                    && index.get_tablename().eq_ignore_ascii_case(tablename)
+                    && !index.is_partial_index()
+                    && index.get_index_seek_column(&table) == Some(column)
+            })
+            .map(|index| index.get_root_page())
     }
 
     pub fn get_metadata(&self) -> Result<Option<Response>> {
@@ -108,18 +91,13 @@ impl DBMetadata {
         Ok(Some(vec![page_size, table_number]))
     }
 
-    fn get_page_size(&self) -> u16 {
-        // The page size is stored at the 16th byte offset, using 2 bytes in big-endian order
-        if let Some(header) = self.page.get_db_header() {
-            return u16::from_be_bytes([header[16], header[17]]);
-        }
-        0
+    fn get_page_size(&self) -> usize {
+        self.header.page_size
     }
 
-    // The number of table is the number of cell on Page1. Because of the file header
-    // the page header is stored at offset 100
+    // The number of table is the number of objects in the sqlite_schema table
     fn get_number_of_table(&self) -> usize {
-        self.page.get_record_number()
+        self.schema.len()
     }
 
     // Print tablenames in alphabetical order
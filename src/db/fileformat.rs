@@ -4,10 +4,14 @@
 //! It is based on SQlite documentation about file format:
 //! [Sqlite fileformat doc](https://www.sqlite.org/fileformat.html)
 //!
-//! It contains three main modules:
+//! It contains five main modules:
+//! * [header] A module to parse the database header
 //! * [page] A module that offer a way to read page in the db
+//! * [cell] A module to parse the cells of a B-tree page
 //! * [record] A module that allows to read one record
 //! * [types]  Type associated of the fileformat
+pub mod cell;
+pub mod header;
 pub mod page;
 pub mod record;
 pub mod types;
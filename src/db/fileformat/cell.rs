@@ -0,0 +1,128 @@
+//! Module to parse B-tree cells.
+//!
+//! A cell format depends on the B-tree page type:
+//! * Table leaf: payload size (varint), rowid (varint), payload, overflow page (u32)
+//! * Table interior: left child page (u32), rowid (varint)
+//! * Index leaf: payload size (varint), payload, overflow page (u32)
+//! * Index interior: left child page (u32), payload size (varint), payload, overflow page (u32)
+//!
+//! When the payload is too big to fit in the page, only the first bytes are stored
+//! in the cell, the rest spills into a linked list of overflow pages.
+//! The overflow page number is only present when the payload spills.
+//!
+//! See 1.6. B-tree Pages in [Sqlite fileformat doc](https://www.sqlite.org/fileformat.html)
+use crate::db::fileformat::{page::BTreeType, types::Varint};
+use anyhow::{Result, anyhow};
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::Cursor;
+
+#[derive(Debug)]
+pub struct Cell<'a> {
+    pub left_child: Option<usize>,
+    pub rowid: Option<i64>,
+    pub payload_size: usize,
+    // The part of the payload stored in the page
+    pub payload: &'a [u8],
+    pub overflow_page: Option<usize>,
+}
+
+impl<'a> Cell<'a> {
+    /// Parse a cell. `usable_size` is the page size minus the reserved bytes
+    /// at the end of each page, see the database header.
+    pub fn new(buffer: &'a [u8], btree_type: &BTreeType, usable_size: usize) -> Result<Self> {
+        let mut offset = 0;
+        let left_child = if btree_type.is_interior() {
+            offset += 4;
+            Some(Cursor::new(buffer).read_u32::<BigEndian>()? as usize)
+        } else {
+            None
+        };
+
+        if *btree_type == BTreeType::InteriorPage {
+            let rowid = Varint::new(&buffer[offset..]);
+            return Ok(Self {
+                left_child,
+                rowid: Some(rowid.varint),
+                payload_size: 0,
+                payload: &[],
+                overflow_page: None,
+            });
+        }
+
+        let payload_size = Varint::new(&buffer[offset..]);
+        offset += payload_size.size;
+        let payload_size = payload_size.varint as usize;
+        let rowid = if *btree_type == BTreeType::LeafPage {
+            let rowid = Varint::new(&buffer[offset..]);
+            offset += rowid.size;
+            Some(rowid.varint)
+        } else {
+            None
+        };
+
+        let local_size = local_payload_size(payload_size, usable_size, btree_type);
+        let end = offset + local_size;
+        if end > buffer.len() {
+            return Err(anyhow!("Cell: payload goes beyond the page"));
+        }
+        let payload = &buffer[offset..end];
+        let overflow_page = if local_size < payload_size {
+            Some(Cursor::new(&buffer[end..]).read_u32::<BigEndian>()? as usize)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            left_child,
+            rowid,
+            payload_size,
+            payload,
+            overflow_page,
+        })
+    }
+}
+
+/// Compute how many bytes of the payload are stored in the cell.
+/// See the description of the payload in 1.6. B-tree Pages:
+/// * X is the maximum payload that can be stored in the cell
+/// * M is the minimum payload that must be stored in the cell when it spills
+fn local_payload_size(payload_size: usize, usable_size: usize, btree_type: &BTreeType) -> usize {
+    let max_local = if *btree_type == BTreeType::LeafPage {
+        usable_size - 35
+    } else {
+        ((usable_size - 12) * 64 / 255) - 23
+    };
+    if payload_size <= max_local {
+        return payload_size;
+    }
+    let min_local = ((usable_size - 12) * 32 / 255) - 23;
+    let local = min_local + ((payload_size - min_local) % (usable_size - 4));
+    if local <= max_local { local } else { min_local }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_keep_small_payload_local() {
+        let cell = Cell::new(&[0x03, 0x01, 0x02, 0x00, 0x01], &BTreeType::LeafPage, 4096).unwrap();
+        assert_eq!(cell.rowid, Some(1));
+        assert_eq!(cell.payload, &[0x02, 0x00, 0x01]);
+        assert_eq!(cell.overflow_page, None);
+    }
+
+    #[test]
+    fn it_should_compute_local_size_of_spilled_table_payload() {
+        // X = 4061, M = 489, K = 489 + (5000 - 489) % 4092 = 908
+        assert_eq!(local_payload_size(5000, 4096, &BTreeType::LeafPage), 908);
+        assert_eq!(local_payload_size(4061, 4096, &BTreeType::LeafPage), 4061);
+    }
+
+    #[test]
+    fn it_should_compute_local_size_of_spilled_index_payload() {
+        // X = 1002, M = 489, K = 489 + (1500 - 489) % 4092 = 1500 > X
+        assert_eq!(local_payload_size(1500, 4096, &BTreeType::LeafIndex), 489);
+        assert_eq!(local_payload_size(1002, 4096, &BTreeType::LeafIndex), 1002);
+    }
+}
@@ -0,0 +1,220 @@
+//! Module that contains the database header.
+//!
+//! The database header is stored in the first 100 bytes of the file.
+//! All multibyte fields are stored in big-endian order.
+//!
+//! See 1.3. The Database Header in [Sqlite fileformat doc](https://www.sqlite.org/fileformat.html)
+use anyhow::{Result, anyhow};
+use byteorder::{BigEndian, ReadBytesExt};
+use std::io::{Cursor, Read};
+
+pub const HEADER_SIZE: usize = 100;
+const MAGIC_STRING: &[u8; 16] = b"SQLite format 3\0";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+impl TextEncoding {
+    fn new(encoding: u32) -> Result<Self> {
+        match encoding {
+            1 => Ok(TextEncoding::Utf8),
+            2 => Ok(TextEncoding::Utf16le),
+            3 => Ok(TextEncoding::Utf16be),
+            _ => Err(anyhow!(
+                "Database header: invalid text encoding {}",
+                encoding
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DbHeader {
+    /// Page size in bytes, a power of two between 512 and 65536
+    pub page_size: usize,
+    /// 1 for legacy, 2 for WAL
+    pub write_version: u8,
+    /// 1 for legacy, 2 for WAL
+    pub read_version: u8,
+    /// Bytes reserved at the end of each page
+    pub reserved_size: u8,
+    pub max_payload_fraction: u8,
+    pub min_payload_fraction: u8,
+    pub leaf_payload_fraction: u8,
+    pub file_change_counter: u32,
+    /// Size of the database in pages
+    pub page_count: u32,
+    pub freelist_trunk_page: u32,
+    pub freelist_count: u32,
+    pub schema_cookie: u32,
+    /// Schema format number, from 1 to 4
+    pub schema_format: u32,
+    pub default_cache_size: u32,
+    /// Largest root B-tree page when in auto-vacuum or incremental-vacuum mode, 0 otherwise
+    pub largest_root_page: u32,
+    pub text_encoding: TextEncoding,
+    pub user_version: u32,
+    pub incremental_vacuum: bool,
+    pub application_id: u32,
+    pub version_valid_for: u32,
+    /// SQLITE_VERSION_NUMBER of the library that last modified the file
+    pub sqlite_version: u32,
+}
+
+impl DbHeader {
+    pub fn new(buffer: &[u8]) -> Result<Self> {
+        if buffer.len() < HEADER_SIZE {
+            return Err(anyhow!(
+                "Database header: file is too small ({} bytes)",
+                buffer.len()
+            ));
+        }
+        let mut cursor = Cursor::new(buffer);
+        let mut magic = [0; 16];
+        cursor.read_exact(&mut magic)?;
+        if &magic != MAGIC_STRING {
+            return Err(anyhow!("Database header: file is not a sqlite database"));
+        }
+
+        // The value 1 represents a page size of 65536
+        let page_size = match cursor.read_u16::<BigEndian>()? {
+            1 => 65536,
+            size => size as usize,
+        };
+        if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+            return Err(anyhow!("Database header: invalid page size {}", page_size));
+        }
+
+        let write_version = cursor.read_u8()?;
+        let read_version = cursor.read_u8()?;
+        let reserved_size = cursor.read_u8()?;
+        // The usable size must be at least 480 bytes
+        if page_size - (reserved_size as usize) < 480 {
+            return Err(anyhow!(
+                "Database header: {} reserved bytes do not fit in {} bytes pages",
+                reserved_size,
+                page_size
+            ));
+        }
+
+        let max_payload_fraction = cursor.read_u8()?;
+        let min_payload_fraction = cursor.read_u8()?;
+        let leaf_payload_fraction = cursor.read_u8()?;
+        if (
+            max_payload_fraction,
+            min_payload_fraction,
+            leaf_payload_fraction,
+        ) != (64, 32, 32)
+        {
+            return Err(anyhow!("Database header: invalid payload fractions"));
+        }
+
+        let file_change_counter = cursor.read_u32::<BigEndian>()?;
+        let page_count = cursor.read_u32::<BigEndian>()?;
+        let freelist_trunk_page = cursor.read_u32::<BigEndian>()?;
+        let freelist_count = cursor.read_u32::<BigEndian>()?;
+        let schema_cookie = cursor.read_u32::<BigEndian>()?;
+        let schema_format = cursor.read_u32::<BigEndian>()?;
+        // A database without schema has a schema format of 0
+        if schema_format > 4 {
+            return Err(anyhow!(
+                "Database header: unsupported schema format {}",
+                schema_format
+            ));
+        }
+        let default_cache_size = cursor.read_u32::<BigEndian>()?;
+        let largest_root_page = cursor.read_u32::<BigEndian>()?;
+        // An empty database has no text encoding yet, it defaults to UTF-8
+        let text_encoding = match cursor.read_u32::<BigEndian>()? {
+            0 => TextEncoding::Utf8,
+            encoding => TextEncoding::new(encoding)?,
+        };
+        let user_version = cursor.read_u32::<BigEndian>()?;
+        let incremental_vacuum = cursor.read_u32::<BigEndian>()? != 0;
+        let application_id = cursor.read_u32::<BigEndian>()?;
+
+        // 20 bytes reserved for expansion
+        cursor.set_position(92);
+        let version_valid_for = cursor.read_u32::<BigEndian>()?;
+        let sqlite_version = cursor.read_u32::<BigEndian>()?;
+
+        Ok(Self {
+            page_size,
+            write_version,
+            read_version,
+            reserved_size,
+            max_payload_fraction,
+            min_payload_fraction,
+            leaf_payload_fraction,
+            file_change_counter,
+            page_count,
+            freelist_trunk_page,
+            freelist_count,
+            schema_cookie,
+            schema_format,
+            default_cache_size,
+            largest_root_page,
+            text_encoding,
+            user_version,
+            incremental_vacuum,
+            application_id,
+            version_valid_for,
+            sqlite_version,
+        })
+    }
+
+    /// Page size minus the bytes reserved at the end of each page
+    pub fn usable_size(&self) -> usize {
+        self.page_size - self.reserved_size as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_buffer(page_size: u16) -> Vec<u8> {
+        let mut buffer = vec![0; HEADER_SIZE];
+        buffer[..16].copy_from_slice(MAGIC_STRING);
+        buffer[16..18].copy_from_slice(&page_size.to_be_bytes());
+        buffer[18] = 1;
+        buffer[19] = 1;
+        buffer[21] = 64;
+        buffer[22] = 32;
+        buffer[23] = 32;
+        buffer[28..32].copy_from_slice(&7u32.to_be_bytes());
+        buffer[44..48].copy_from_slice(&4u32.to_be_bytes());
+        buffer[56..60].copy_from_slice(&2u32.to_be_bytes());
+        buffer[96..100].copy_from_slice(&3045001u32.to_be_bytes());
+        buffer
+    }
+
+    #[test]
+    fn it_should_parse_header_fields() {
+        let header = DbHeader::new(&header_buffer(4096)).unwrap();
+        assert_eq!(header.page_size, 4096);
+        assert_eq!(header.page_count, 7);
+        assert_eq!(header.schema_format, 4);
+        assert_eq!(header.text_encoding, TextEncoding::Utf16le);
+        assert_eq!(header.sqlite_version, 3045001);
+    }
+
+    #[test]
+    fn it_should_read_page_size_one_as_65536() {
+        let header = DbHeader::new(&header_buffer(1)).unwrap();
+        assert_eq!(header.page_size, 65536);
+    }
+
+    #[test]
+    fn it_should_reject_invalid_headers() {
+        let mut buffer = header_buffer(4096);
+        buffer[0] = b's';
+        assert!(DbHeader::new(&buffer).is_err());
+        assert!(DbHeader::new(&header_buffer(1000)).is_err());
+        assert!(DbHeader::new(&header_buffer(4096)[..50]).is_err());
+    }
+}
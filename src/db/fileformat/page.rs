@@ -12,11 +12,10 @@
 //! * a header [PageHeader]
 //! * a cell pointer array: array of u16 offsets to the cells
 //!
-//! A `cell` contains a record. See [Record] module for more information about records.
+//! A `cell` contains a record. See [Record](crate::db::fileformat::record::Record) module for more information about records.
 //! But Cell format depends on the BTree type. See 1.6. B-tree Pages in
 //! [Sqlite fileformat documentation](https://www.sqlite.org/fileformat.html)
-use crate::db::{fileformat::record::Record, table::Table};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use byteorder::{BigEndian, ReadBytesExt};
 use std::io::Cursor;
 
@@ -31,12 +30,11 @@ impl Page {
     // See documentation for the why https://www.sqlite.org/fileformat.html
     // THe first page contains the file header that measures 100 bytes.
     pub fn new(buffer: Vec<u8>, page_number: usize) -> Result<Self> {
-        let page_header;
-        if page_number == 1 {
-            page_header = PageHeader::new(&buffer[100..])?;
+        let page_header = if page_number == 1 {
+            PageHeader::new(&buffer[100..])?
         } else {
-            page_header = PageHeader::new(&buffer)?;
-        }
+            PageHeader::new(&buffer)?
+        };
         Ok(Self {
             buffer,
             page_number,
@@ -78,10 +76,10 @@ impl Page {
     pub fn get_cell_pointer_array(&self) -> &[u8] {
         let buffer = self.get_page_buffer();
         let cell_number = self.page_header.cell_number;
-        if self.page_header.btree_type == BTreeType::InteriorPage {
-            return &buffer[12..12 + cell_number as usize * 2];
+        if self.page_header.btree_type.is_interior() {
+            &buffer[12..12 + cell_number * 2]
         } else {
-            return &buffer[8..8 + cell_number as usize * 2];
+            &buffer[8..8 + cell_number * 2]
         }
     }
 
@@ -96,29 +94,24 @@ impl Page {
         }
     }
 
-    pub fn get_all_records<'a>(&self, table: &'a Table) -> Result<Vec<Record<'a>>> {
-        let mut rows = vec![];
-        let cell_array = self.get_cell_pointer_array();
-        let mut cursor = Cursor::new(cell_array);
-
-        for _ in 0..self.get_record_number() {
-            let offset = cursor.read_u16::<BigEndian>()? as usize;
-            let record = Record::new(&self.get_slice(offset, None), table)?;
-            rows.push(record);
+    /// Get the content of the nth cell, starting at the cell offset up to the end of the page.
+    pub fn get_cell(&self, index: usize) -> Result<&[u8]> {
+        if index >= self.get_record_number() {
+            return Err(anyhow!(
+                "Page {}: cell {} out of range",
+                self.page_number,
+                index
+            ));
         }
-
-        Ok(rows)
+        let mut cursor = Cursor::new(&self.get_cell_pointer_array()[index * 2..]);
+        let offset = cursor.read_u16::<BigEndian>()? as usize;
+        Ok(self.get_slice(offset, None))
     }
 
-    /// This function is used to iterate over records in a page
-    pub fn get_nth_record<'a>(&self, index: usize, schema_table: &'a Table) -> Result<Record<'a>> {
-        let cell_array_offset = index * 2;
-        let cell_array = self.get_cell_pointer_array();
-        let mut cursor = Cursor::new(&cell_array[cell_array_offset..]);
-        let offset = cursor.read_u16::<BigEndian>()? as usize;
-        let record = Record::new(&self.get_slice(offset as usize, None), schema_table)
-            .expect("Error: indexing record, file parsing failed");
-        Ok(record)
+    /// Interior cells start with the 4 bytes page number of their left child.
+    pub fn get_left_child(&self, index: usize) -> Result<usize> {
+        let mut cursor = Cursor::new(self.get_cell(index)?);
+        Ok(cursor.read_u32::<BigEndian>()? as usize)
     }
 }
 
@@ -149,6 +142,10 @@ impl BTreeType {
             _ => panic!("Error: Number type invalid"),
         }
     }
+
+    pub fn is_interior(&self) -> bool {
+        matches!(self, BTreeType::InteriorIndex | BTreeType::InteriorPage)
+    }
 }
 
 pub struct PageHeader {
@@ -163,13 +160,24 @@ pub struct PageHeader {
 impl PageHeader {
     fn new(buffer: &[u8]) -> Result<Self> {
         let mut cursor = Cursor::new(buffer);
+        let btree_type = BTreeType::new(cursor.read_u8()?);
+        let start_free = cursor.read_u16::<BigEndian>()? as usize;
+        let cell_number = cursor.read_u16::<BigEndian>()? as usize;
+        let start_content = cursor.read_u16::<BigEndian>()? as usize;
+        let frag_number = cursor.read_u8()?;
+        // The right most pointer is only part of interior page headers
+        let right_most_pointer = if btree_type.is_interior() {
+            cursor.read_u32::<BigEndian>()? as usize
+        } else {
+            0
+        };
         Ok(PageHeader {
-            btree_type: BTreeType::new(cursor.read_u8()?),
-            start_free: cursor.read_u16::<BigEndian>()? as usize,
-            cell_number: cursor.read_u16::<BigEndian>()? as usize,
-            start_content: cursor.read_u16::<BigEndian>()? as usize,
-            frag_number: cursor.read_u8()?,
-            right_most_pointer: cursor.read_u16::<BigEndian>()? as usize,
+            btree_type,
+            start_free,
+            cell_number,
+            start_content,
+            frag_number,
+            right_most_pointer,
         })
     }
 }
@@ -1,9 +1,13 @@
 /// Module to handle Record parsing from a cell payload
 /// ! See 2.1 Record Format in https://www.sqlite.org/fileformat.html
 /// A record is contains by a Cell.
-use crate::db::{fileformat::types::Varint, table::Table};
+use crate::db::{
+    fileformat::{header::TextEncoding, types::Varint},
+    table::Table,
+};
 use crate::executor::db_response::RType;
-use anyhow::Result;
+use crate::parser::create_table::Affinity;
+use anyhow::{Result, anyhow};
 use byteorder::{BigEndian, ReadBytesExt};
 use std::collections::HashMap;
 use std::io::{Cursor, Read};
@@ -11,91 +15,207 @@ use std::io::{Cursor, Read};
 #[allow(unused)]
 #[derive(Debug)]
 pub struct Record<'a> {
-    cell_size: usize,
+    payload_size: usize,
     pub rowid: usize,
     header: RecordHeader,
-    record_start: usize, // When actual record start, after cell header + record header + rowid
+    record_start: usize, // When actual record start, after record header
     fields: HashMap<&'a str, RType>,
+    table: &'a Table,
+    // Encoding of the database text, it orders text values with the BINARY collation
+    encoding: TextEncoding,
 }
 
 impl<'a> Record<'a> {
-    pub fn new(buffer: &[u8], table: &'a Table) -> Result<Self> {
-        // Parsing cell Header
-        let cell_size = Varint::new(buffer);
-        let rowid = Varint::new(&buffer[cell_size.size..]);
-
+    /// Parse a record from the complete cell payload.
+    /// The payload must already contains the bytes stored in overflow pages.
+    /// Rows of WITHOUT ROWID tables have no rowid, it is 0.
+    pub fn new(
+        payload: &[u8],
+        rowid: i64,
+        table: &'a Table,
+        encoding: TextEncoding,
+    ) -> Result<Self> {
         // Parsing record header
-        let buffer_start = cell_size.size + rowid.size;
-        let header = RecordHeader::new(&buffer[buffer_start..]);
+        let header = RecordHeader::new(payload)?;
 
         // Parsing record
-        let record_start = cell_size.size + rowid.size + header.size;
+        let record_start = header.size;
+        let mut cursor = Cursor::new(&payload[record_start..]);
+        let mut values = header
+            .col_serial_types
+            .iter()
+            .map(|col_serial_type| {
+                Self::from_col_serial_type(col_serial_type, &mut cursor, encoding)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let mut fields: HashMap<&str, RType> = HashMap::new();
-        let mut cursor = Cursor::new(&buffer[record_start..]);
-        for (i, col_serial_type) in header.col_serial_types.iter().enumerate() {
+        for i in 0..table.cols_name.len() {
             let key = table.get_column_name(i);
-            let field = if key == "id" {
-                RType::Num(rowid.varint)
+            let field = if table.is_rowid_alias(i) {
+                RType::Num(rowid)
+            } else if let Some(value) = values.get_mut(table.get_record_position(i)) {
+                std::mem::replace(value, RType::Null)
             } else {
-                Self::from_col_serial_type(&col_serial_type, &mut cursor)?
+                table.get_column_default(i)
             };
-            fields.insert(key, field);
+            fields.insert(
+                key,
+                Self::apply_affinity(field, table.get_column_affinity(i)),
+            );
         }
         Ok(Self {
-            cell_size: cell_size.varint as usize,
-            rowid: rowid.varint as usize,
+            payload_size: payload.len(),
+            rowid: rowid as usize,
             header,
             record_start,
             fields,
+            table,
+            encoding,
         })
     }
 
+    /// Build a record from values already decoded, like the rows of a view.
+    /// Values are in the table column order.
+    pub fn from_values(values: Vec<RType>, table: &'a Table, encoding: TextEncoding) -> Self {
+        let fields = table
+            .cols_name
+            .iter()
+            .map(|col_name| col_name.as_str())
+            .zip(values)
+            .collect();
+        Self {
+            payload_size: 0,
+            rowid: 0,
+            header: RecordHeader {
+                size: 0,
+                col_serial_types: vec![],
+            },
+            record_start: 0,
+            fields,
+            table,
+            encoding,
+        }
+    }
+
+    /// Sqlite stores REAL values without fractional part as integers to save space,
+    /// they are converted back when read.
+    fn apply_affinity(value: RType, affinity: Affinity) -> RType {
+        match (value, affinity) {
+            (RType::Num(value), Affinity::Real) => RType::Real(value as f64),
+            (value, _) => value,
+        }
+    }
+
+    /// Decode all the values of a record, in the record order.
+    /// Used for index records that are not associated to table columns.
+    pub fn read_values(payload: &[u8], encoding: TextEncoding) -> Result<Vec<RType>> {
+        let header = RecordHeader::new(payload)?;
+        let mut cursor = Cursor::new(&payload[header.size..]);
+        header
+            .col_serial_types
+            .iter()
+            .map(|col_serial_type| {
+                Self::from_col_serial_type(col_serial_type, &mut cursor, encoding)
+            })
+            .collect()
+    }
+
     pub fn take_fields(&mut self) -> HashMap<&str, RType> {
         std::mem::take(&mut self.fields)
     }
 
     /// Move out a value from the record
     pub fn take_field(&mut self, key: &str) -> Option<RType> {
-        return self.fields.remove(key);
+        self.fields.remove(key)
     }
 
     pub fn from_col_serial_type(
         serial_type: &ColSerialType,
         cursor: &mut Cursor<&[u8]>,
+        encoding: TextEncoding,
     ) -> Result<RType> {
         let col = match serial_type {
             ColSerialType::Null => RType::Null,
             ColSerialType::Vu8 => RType::Num(cursor.read_i8()? as i64),
             ColSerialType::Vu16 => RType::Num(cursor.read_i16::<BigEndian>()? as i64),
+            ColSerialType::Vu24 => RType::Num(cursor.read_i24::<BigEndian>()? as i64),
             ColSerialType::Vu32 => RType::Num(cursor.read_i32::<BigEndian>()? as i64),
-            ColSerialType::Vu48 => RType::Num(Self::get_i48(cursor)?),
-            ColSerialType::Vu64 => RType::Num(cursor.read_i64::<BigEndian>()? as i64),
-            ColSerialType::Vf64 => RType::Num(cursor.read_f64::<BigEndian>()? as i64),
+            ColSerialType::Vu48 => RType::Num(cursor.read_i48::<BigEndian>()?),
+            ColSerialType::Vu64 => RType::Num(cursor.read_i64::<BigEndian>()?),
+            ColSerialType::Vf64 => RType::Real(cursor.read_f64::<BigEndian>()?),
             ColSerialType::V0 => RType::Num(0),
             ColSerialType::V1 => RType::Num(1),
-            ColSerialType::Variable => todo!("ColSeriableType variable"),
             ColSerialType::Blob(size) => {
-                let mut blob = Vec::new();
-                blob.resize(*size, 0);
+                let mut blob = vec![0; *size];
                 cursor.read_exact(&mut blob)?;
                 RType::Blob(blob)
             }
             ColSerialType::Str(size) => {
-                let mut buffer = Vec::new();
-                buffer.resize(*size, 0);
+                let mut buffer = vec![0; *size];
                 cursor.read_exact(&mut buffer)?;
-                RType::Str(String::from_utf8(buffer)?)
+                RType::Str(Self::decode_text(buffer, encoding)?)
             }
         };
         Ok(col)
     }
 
-    pub fn get_i48(_cursor: &mut Cursor<&[u8]>) -> Result<i64> {
-        todo!("Handle i48 field type in record")
+    /// Text values are stored using the database text encoding, see the database header.
+    fn decode_text(buffer: Vec<u8>, encoding: TextEncoding) -> Result<String> {
+        let code_units = buffer.chunks_exact(2);
+        if !code_units.remainder().is_empty() && encoding != TextEncoding::Utf8 {
+            return Err(anyhow!("Text value has an odd number of bytes in UTF-16"));
+        }
+        let text = match encoding {
+            TextEncoding::Utf8 => String::from_utf8(buffer)?,
+            TextEncoding::Utf16le => String::from_utf16(
+                &code_units
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            )?,
+            TextEncoding::Utf16be => String::from_utf16(
+                &code_units
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>(),
+            )?,
+        };
+        Ok(text)
     }
 
     pub fn get_column_value(&self, key: &str) -> &RType {
-        &self.fields.get(key).unwrap()
+        self.fields.get(key).unwrap()
+    }
+
+    /// Value of a column, or the rowid for `rowid`, `oid` and `_rowid_`.
+    /// None if there is no such column.
+    pub fn get_value(&self, key: &str) -> Option<RType> {
+        match self.fields.get(key) {
+            Some(value) => Some(value.clone()),
+            None if self.table.is_rowid_column(key) => Some(RType::Num(self.rowid as i64)),
+            None => None,
+        }
+    }
+
+    pub fn get_table(&self) -> &'a Table {
+        self.table
+    }
+
+    pub fn get_encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// Values of all the columns, in the order of the table definition
+    pub fn get_values(&self) -> Vec<RType> {
+        self.table
+            .cols_name
+            .iter()
+            .map(|col_name| self.get_column_value(col_name).clone())
+            .collect()
+    }
+
+    /// Affinity of a column of the record table, None if there is no such column
+    pub fn get_column_affinity(&self, key: &str) -> Option<Affinity> {
+        self.table.find_column_affinity(key)
     }
 }
 
@@ -104,13 +224,13 @@ pub enum ColSerialType {
     Null,
     Vu8,
     Vu16,
+    Vu24,
     Vu32,
     Vu48,
     Vu64,
     Vf64,
     V0,
     V1,
-    Variable,
     Blob(usize),
     Str(usize),
 }
@@ -119,29 +239,31 @@ pub enum ColSerialType {
 // See 2.1 Record Format in https://www.sqlite.org/fileformat.html
 // We don't store the value of the type yet.
 impl ColSerialType {
-    pub fn new(serial_type: usize) -> ColSerialType {
-        match serial_type {
+    pub fn new(serial_type: i64) -> Result<ColSerialType> {
+        let col_serial_type = match serial_type {
             0 => ColSerialType::Null,
             1 => ColSerialType::Vu8,
             2 => ColSerialType::Vu16,
-            3 => ColSerialType::Vu32,
-            4 => ColSerialType::Vu48,
-            5 => ColSerialType::Vu64,
-            6 => ColSerialType::Vf64,
-            7 => ColSerialType::V0,
-            8 => ColSerialType::V1,
-            10 | 11 => ColSerialType::Variable,
-            _ => {
-                if serial_type >= 12 && serial_type % 2 == 0 {
-                    let size = (serial_type - 12) / 2;
-                    return ColSerialType::Blob(size);
-                } else if serial_type > 13 && serial_type % 2 != 0 {
-                    let size = (serial_type - 13) / 2;
-                    return ColSerialType::Str(size);
-                }
-                panic!("Error: serial type is not valid");
+            3 => ColSerialType::Vu24,
+            4 => ColSerialType::Vu32,
+            5 => ColSerialType::Vu48,
+            6 => ColSerialType::Vu64,
+            7 => ColSerialType::Vf64,
+            8 => ColSerialType::V0,
+            9 => ColSerialType::V1,
+            10 | 11 => {
+                return Err(anyhow!(
+                    "Serial type {} is reserved for internal use",
+                    serial_type
+                ));
             }
-        }
+            _ if serial_type >= 12 && serial_type % 2 == 0 => {
+                ColSerialType::Blob(((serial_type - 12) / 2) as usize)
+            }
+            _ if serial_type >= 13 => ColSerialType::Str(((serial_type - 13) / 2) as usize),
+            _ => return Err(anyhow!("Serial type {} is not valid", serial_type)),
+        };
+        Ok(col_serial_type)
     }
 
     /// Return the size in bytes
@@ -151,13 +273,13 @@ impl ColSerialType {
             ColSerialType::Null => 0,
             ColSerialType::Vu8 => 1,
             ColSerialType::Vu16 => 2,
+            ColSerialType::Vu24 => 3,
             ColSerialType::Vu32 => 4,
             ColSerialType::Vu48 => 6,
             ColSerialType::Vu64 => 8,
             ColSerialType::Vf64 => 8,
             ColSerialType::V0 => 0,
             ColSerialType::V1 => 0,
-            ColSerialType::Variable => panic!("Got variable col type in Record"),
             ColSerialType::Blob(size) => size,
             ColSerialType::Str(size) => size,
         }
@@ -172,24 +294,87 @@ pub struct RecordHeader {
 }
 
 impl RecordHeader {
-    pub fn new(buffer: &[u8]) -> Self {
+    pub fn new(buffer: &[u8]) -> Result<Self> {
         let cell_header_size = Varint::new(buffer);
+        let size = cell_header_size.varint as usize;
+        if size > buffer.len() {
+            return Err(anyhow!("Record header goes beyond the payload"));
+        }
         let mut col_serial_types = vec![];
         // We start parsing header columns value type after
         // the cell size memory value.
         let mut offset = cell_header_size.size;
-        loop {
-            let col_type = Varint::new(&buffer[offset..]);
-
-            col_serial_types.push(ColSerialType::new(col_type.varint as usize));
+        while offset < size {
+            let col_type = Varint::new(&buffer[offset..size]);
+            col_serial_types.push(ColSerialType::new(col_type.varint)?);
             offset += col_type.size;
-            if offset == cell_header_size.varint as usize {
-                break;
-            }
         }
-        Self {
-            size: cell_header_size.varint as usize,
+        Ok(Self {
+            size,
             col_serial_types,
-        }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_decode_every_integer_serial_type() {
+        // Header: size, then serial types 1 to 6, 8 and 9
+        let mut payload = vec![9, 1, 2, 3, 4, 5, 6, 8, 9];
+        payload.extend_from_slice(&[0xff]);
+        payload.extend_from_slice(&[0x01, 0x00]);
+        payload.extend_from_slice(&[0xff, 0xff, 0xfe]);
+        payload.extend_from_slice(&[0x7f, 0xff, 0xff, 0xff]);
+        payload.extend_from_slice(&[0x00, 0x80, 0x00, 0x00, 0x00, 0x00]);
+        payload.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfd]);
+        let values = Record::read_values(&payload, TextEncoding::Utf8).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                RType::Num(-1),
+                RType::Num(256),
+                RType::Num(-2),
+                RType::Num(i32::MAX as i64),
+                RType::Num(1 << 39),
+                RType::Num(-3),
+                RType::Num(0),
+                RType::Num(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_decode_negative_i48() {
+        let payload = [2, 5, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe];
+        let values = Record::read_values(&payload, TextEncoding::Utf8).unwrap();
+        assert_eq!(values, vec![RType::Num(-2)]);
+    }
+
+    #[test]
+    fn it_should_decode_empty_text_and_blob() {
+        let payload = [3, 13, 12];
+        let values = Record::read_values(&payload, TextEncoding::Utf8).unwrap();
+        assert_eq!(values, vec![RType::Str(String::new()), RType::Blob(vec![])]);
+    }
+
+    #[test]
+    fn it_should_decode_utf16_text() {
+        // "hé" is 2 UTF-16 code units: 4 bytes, serial type 13 + 2 * 4
+        let payload = [2, 21, 0x68, 0x00, 0xe9, 0x00];
+        let values = Record::read_values(&payload, TextEncoding::Utf16le).unwrap();
+        assert_eq!(values, vec![RType::Str("hé".to_string())]);
+        let payload = [2, 21, 0x00, 0x68, 0x00, 0xe9];
+        let values = Record::read_values(&payload, TextEncoding::Utf16be).unwrap();
+        assert_eq!(values, vec![RType::Str("hé".to_string())]);
+    }
+
+    #[test]
+    fn it_should_fail_on_reserved_serial_types() {
+        assert!(ColSerialType::new(10).is_err());
+        assert!(ColSerialType::new(11).is_err());
+        assert!(Record::read_values(&[2, 10], TextEncoding::Utf8).is_err());
     }
 }
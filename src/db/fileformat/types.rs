@@ -41,18 +41,18 @@ mod tests {
 
     #[test]
     fn test_varint_less_than_240() {
-        let varint = Varint::new(&vec![0x2B]);
+        let varint = Varint::new(&[0x2B]);
         assert_eq!((43, 1), (varint.varint, varint.size));
     }
 
     #[test]
     fn test_varint_multi_bytes() {
-        let varint = Varint::new(&vec![0x81, 0x47]);
-        assert_eq!((43, 1), (varint.varint, varint.size));
+        let varint = Varint::new(&[0x81, 0x47]);
+        assert_eq!((199, 2), (varint.varint, varint.size));
     }
     #[test]
     fn read_nine_byte_varint() {
-        let varint = Varint::new(&vec![0xff; 9]);
-        assert_eq!((43, 1), (varint.varint, varint.size));
+        let varint = Varint::new(&[0xff; 9]);
+        assert_eq!((-1, 9), (varint.varint, varint.size));
     }
 }
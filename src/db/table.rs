@@ -1,8 +1,27 @@
 //! Module for handling database table schemas.
+//!
+//! Tables and indexes are built from their sql definition,
+//! parsed with the [Parser](crate::parser::Parser).
+use crate::executor::db_response::RType;
+use crate::parser::{
+    Parser,
+    create_index::CreateIndexStatement,
+    create_table::{Affinity, CreateTableStatement, DefaultValue, IndexedColumn},
+    create_view::CreateViewStatement,
+    statement::Statement,
+};
+use anyhow::{Error, Result, anyhow};
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::str::FromStr;
 
-pub type SchemaTable = HashMap<String, Table>;
+// Tables are shared: a query can read the same table several times
+pub type SchemaTable = HashMap<String, Rc<Table>>;
 
+// Names of the rowid, when no column of the table has the name
+const ROWID_NAMES: [&str; 3] = ["rowid", "oid", "_rowid_"];
+
+#[derive(PartialEq, Debug)]
 pub enum TableType {
     Table,
     Index,
@@ -10,26 +29,50 @@ pub enum TableType {
     Trigger,
 }
 
-impl TableType {
-    pub fn from_str(str: &str) -> Self {
+impl FromStr for TableType {
+    type Err = Error;
+
+    fn from_str(str: &str) -> Result<Self> {
         match str {
-            "table" => TableType::Table,
-            "index" => TableType::Index,
-            "view" => TableType::View,
-            "trigger" => TableType::Trigger,
-            _ => panic!("Wront table type"),
+            "table" => Ok(TableType::Table),
+            "index" => Ok(TableType::Index),
+            "view" => Ok(TableType::View),
+            "trigger" => Ok(TableType::Trigger),
+            _ => Err(anyhow!("Wrong table type: {}", str)),
         }
     }
 }
 
-pub enum ColType {}
+/// Parsed sql of a schema object
+#[derive(Debug)]
+pub enum Definition {
+    Table(CreateTableStatement),
+    Index(CreateIndexStatement),
+    View(Box<CreateViewStatement>),
+    // Columns of the tables of a join, each one is the column at an index of a table
+    Join(Vec<(Rc<Table>, usize)>),
+    // Error of a definition that cannot be parsed, returned when the object is queried
+    Invalid(String),
+    // Triggers, virtual tables and indexes created for constraints
+    None,
+}
 
 #[allow(unused)]
+#[derive(Debug)]
 pub struct Table {
     table_type: TableType,
     name: String,
+    // Name of the table the object is associated with. For tables, it is the table name.
+    tablename: String,
     root_page: usize,
     tabledef: String,
+    definition: Definition,
+    // Index of the INTEGER PRIMARY KEY column
+    rowid_alias: Option<usize>,
+    // Primary key of a WITHOUT ROWID table, empty for other tables
+    primary_key: Vec<IndexedColumn>,
+    // Position of each column in the stored records
+    record_positions: Vec<usize>,
     pub cols_name: Vec<String>,
 }
 
@@ -37,40 +80,322 @@ impl Table {
     pub fn new(
         table_type: String,
         name: String,
+        tablename: String,
         rootpage: usize,
         tabledef: String,
-        cols_name: Vec<String>,
-    ) -> Self {
-        Self {
-            table_type: TableType::from_str(&table_type),
-            name: name,
+    ) -> Result<Self> {
+        let table_type = TableType::from_str(&table_type)?;
+        // An object that cannot be parsed, like a view using an unsupported select,
+        // does not prevent reading the other ones
+        let definition = Self::parse_definition(&table_type, rootpage, &tabledef)
+            .unwrap_or_else(|error| Definition::Invalid(format!("{:#}", error)));
+        let cols_name = match &definition {
+            Definition::Table(create_table) => create_table
+                .columns
+                .iter()
+                .map(|column| column.name.to_string())
+                .collect(),
+            Definition::Index(create_index) => create_index
+                .columns
+                .iter()
+                .map(|column| column.name.to_string())
+                .collect(),
+            // Without column list, the columns of a view are known once its select is run
+            Definition::View(create_view) => create_view.columns.clone(),
+            Definition::Join(_) | Definition::Invalid(_) | Definition::None => vec![],
+        };
+        let rowid_alias = match &definition {
+            Definition::Table(create_table) => create_table.get_rowid_alias(),
+            _ => None,
+        };
+        let primary_key = match &definition {
+            Definition::Table(create_table) if create_table.without_rowid => {
+                create_table.get_primary_key()
+            }
+            _ => vec![],
+        };
+        let record_positions = Self::get_record_positions(&cols_name, &primary_key);
+        Ok(Self {
+            table_type,
+            name,
+            tablename,
             root_page: rootpage,
-            tabledef: tabledef,
+            tabledef,
+            definition,
+            rowid_alias,
+            primary_key,
+            record_positions,
+            cols_name,
+        })
+    }
+
+    // Records of WITHOUT ROWID tables start with the primary key columns,
+    // followed by the other columns in the table order.
+    fn get_record_positions(cols_name: &[String], primary_key: &[IndexedColumn]) -> Vec<usize> {
+        let record_order = primary_key
+            .iter()
+            .filter_map(|key| cols_name.iter().position(|name| *name == key.name))
+            .chain(
+                (0..cols_name.len())
+                    .filter(|index| !primary_key.iter().any(|key| key.name == cols_name[*index])),
+            )
+            .collect::<Vec<_>>();
+        (0..cols_name.len())
+            .map(|index| {
+                record_order
+                    .iter()
+                    .position(|position| *position == index)
+                    .expect("Every column is in the record order")
+            })
+            .collect()
+    }
+
+    fn parse_definition(
+        table_type: &TableType,
+        rootpage: usize,
+        tabledef: &str,
+    ) -> Result<Definition> {
+        match table_type {
+            // Virtual tables have no B-tree.
+            // Indexes created for UNIQUE and PRIMARY KEY constraints have no sql.
+            TableType::Table | TableType::Index if rootpage == 0 || tabledef.is_empty() => {
+                return Ok(Definition::None);
+            }
+            TableType::Trigger => return Ok(Definition::None),
+            _ => {}
+        }
+        match Parser::new(tabledef).next() {
+            Some(Ok(Statement::CreateTable(create_table))) => Ok(Definition::Table(create_table)),
+            Some(Ok(Statement::CreateIndex(create_index))) => Ok(Definition::Index(create_index)),
+            Some(Ok(Statement::CreateView(create_view))) => {
+                Ok(Definition::View(Box::new(create_view)))
+            }
+            Some(Err(error)) => Err(error),
+            _ => Err(anyhow!(
+                "expected CREATE TABLE, CREATE INDEX or CREATE VIEW"
+            )),
+        }
+    }
+
+    /// Table holding the rows of an expanded view, that are computed at query time.
+    pub fn from_view_columns(name: &str, cols_name: Vec<String>) -> Self {
+        Self {
+            table_type: TableType::View,
+            name: name.to_string(),
+            tablename: name.to_string(),
+            root_page: 0,
+            tabledef: "".to_string(),
+            definition: Definition::None,
+            rowid_alias: None,
+            primary_key: vec![],
+            record_positions: Self::get_record_positions(&cols_name, &[]),
+            cols_name,
+        }
+    }
+
+    /// Table holding the rows of a join: the columns of each table, in join order,
+    /// are named `qualifier.column`.
+    pub fn from_join(tables: &[(&str, Rc<Table>)]) -> Self {
+        let mut cols_name = vec![];
+        let mut columns = vec![];
+        for (qualifier, table) in tables {
+            for (index, col_name) in table.cols_name.iter().enumerate() {
+                cols_name.push(format!("{}.{}", qualifier, col_name));
+                columns.push((table.clone(), index));
+            }
+        }
+        Self {
+            table_type: TableType::View,
+            name: "join".to_string(),
+            tablename: "join".to_string(),
+            root_page: 0,
+            tabledef: "".to_string(),
+            definition: Definition::Join(columns),
+            rowid_alias: None,
+            primary_key: vec![],
+            record_positions: Self::get_record_positions(&cols_name, &[]),
             cols_name,
         }
     }
 
     pub fn schema_table() -> Self {
+        let cols_name = vec![
+            "table_type".to_string(),
+            "name".to_string(),
+            "tablename".to_string(),
+            "rootpage".to_string(),
+            "tabledef".to_string(),
+        ];
         Self {
-            table_type: TableType::from_str("table"),
+            table_type: TableType::Table,
             name: "Schema".to_string(),
-            root_page: 0,
+            tablename: "Schema".to_string(),
+            // sqlite_schema is always rooted at page 1
+            root_page: 1,
             tabledef: "".to_string(),
-            cols_name: vec![
-                "table_type".to_string(),
-                "name".to_string(),
-                "tablename".to_string(),
-                "rootpage".to_string(),
-                "tabledef".to_string(),
-            ],
+            definition: Definition::None,
+            rowid_alias: None,
+            primary_key: vec![],
+            record_positions: Self::get_record_positions(&cols_name, &[]),
+            cols_name,
         }
     }
 
     pub fn get_column_name(&self, index: usize) -> &str {
-        return &self.cols_name[index];
+        &self.cols_name[index]
+    }
+
+    pub fn get_column_index(&self, name: &str) -> Option<usize> {
+        self.cols_name.iter().position(|col_name| col_name == name)
+    }
+
+    pub fn get_definition(&self) -> &Definition {
+        &self.definition
+    }
+
+    /// Fails when the definition of the object could not be parsed
+    pub fn check_definition(&self) -> Result<()> {
+        match &self.definition {
+            Definition::Invalid(error) => Err(anyhow!(
+                "Schema: invalid definition for {}: {}",
+                self.name,
+                error
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Affinity of the column at `index`.
+    /// Columns without definition, like the sqlite_schema ones, have the BLOB affinity.
+    pub fn get_column_affinity(&self, index: usize) -> Affinity {
+        match &self.definition {
+            Definition::Table(create_table) => create_table.columns[index].affinity,
+            Definition::Join(columns) => {
+                let (table, index) = &columns[index];
+                table.get_column_affinity(*index)
+            }
+            _ => Affinity::Blob,
+        }
+    }
+
+    /// Collation declared on the column at `index`, BINARY is used when there is none.
+    pub fn get_column_collation(&self, index: usize) -> Option<&str> {
+        match &self.definition {
+            Definition::Table(create_table) => create_table.columns[index].get_collation(),
+            Definition::Join(columns) => {
+                let (table, index) = &columns[index];
+                table.get_column_collation(*index)
+            }
+            _ => None,
+        }
+    }
+
+    /// Value of a column that is missing from a record.
+    /// Records written before an ALTER TABLE ADD COLUMN do not have the new columns.
+    pub fn get_column_default(&self, index: usize) -> RType {
+        let Definition::Table(create_table) = &self.definition else {
+            return RType::Null;
+        };
+        match create_table.columns[index].get_default() {
+            Some(DefaultValue::Literal(value)) => value.clone(),
+            _ => RType::Null,
+        }
+    }
+
+    /// Partial indexes only contain the rows matching their WHERE clause
+    pub fn is_partial_index(&self) -> bool {
+        matches!(
+            &self.definition,
+            Definition::Index(create_index) if create_index.where_clause.is_some()
+        )
+    }
+
+    /// Returns the column that is an alias for the rowid.
+    /// The column value is not stored in the record, it is the cell rowid.
+    pub fn get_rowid_alias(&self) -> Option<&str> {
+        self.rowid_alias.map(|index| self.get_column_name(index))
+    }
+
+    pub fn is_rowid_alias(&self, index: usize) -> bool {
+        self.rowid_alias == Some(index)
+    }
+
+    /// Whether `name` refers to the rowid: the rowid alias, or `rowid`, `oid` and `_rowid_`
+    /// when no column has that name. WITHOUT ROWID tables and views have no rowid.
+    pub fn is_rowid_column(&self, name: &str) -> bool {
+        match self.get_column_index(name) {
+            Some(index) => self.is_rowid_alias(index),
+            None => {
+                self.table_type == TableType::Table
+                    && !self.is_without_rowid()
+                    && ROWID_NAMES.contains(&name)
+            }
+        }
+    }
+
+    /// Affinity of the column named `name`, the rowid has the INTEGER affinity.
+    /// None if there is no such column.
+    pub fn find_column_affinity(&self, name: &str) -> Option<Affinity> {
+        match self.get_column_index(name) {
+            Some(index) => Some(self.get_column_affinity(index)),
+            None => self.is_rowid_column(name).then_some(Affinity::Integer),
+        }
+    }
+
+    /// WITHOUT ROWID tables are stored in an index B-tree keyed by their primary key.
+    pub fn is_without_rowid(&self) -> bool {
+        !self.primary_key.is_empty()
+    }
+
+    /// Position of the column at `index` in the stored records
+    pub fn get_record_position(&self, index: usize) -> usize {
+        self.record_positions[index]
+    }
+
+    /// Leading primary key column of a WITHOUT ROWID table, when the table B-tree
+    /// can be searched on it: the B-tree must be sorted in ascending order with
+    /// the BINARY collation, the collation comparisons on the column use.
+    pub fn get_primary_key_seek_column(&self) -> Option<&str> {
+        let key = self.primary_key.first()?;
+        (key.is_binary_ascending() && self.is_binary_column(&key.name)).then_some(key.name.as_str())
+    }
+
+    /// Leading column of an index on `table`, when the index B-tree can be searched
+    /// on it, like [get_primary_key_seek_column](Table::get_primary_key_seek_column).
+    pub fn get_index_seek_column(&self, table: &Table) -> Option<&str> {
+        let Definition::Index(create_index) = &self.definition else {
+            return None;
+        };
+        let key = create_index.columns.first()?;
+        (key.is_binary_ascending() && table.is_binary_column(&key.name))
+            .then_some(key.name.as_str())
+    }
+
+    // Comparisons on a column use its declared collation
+    fn is_binary_column(&self, name: &str) -> bool {
+        self.get_column_index(name)
+            .and_then(|index| self.get_column_collation(index))
+            .is_none_or(|collation| collation.eq_ignore_ascii_case("binary"))
+    }
+
+    /// Number of columns of the primary key of a WITHOUT ROWID table
+    pub fn get_primary_key_len(&self) -> usize {
+        self.primary_key.len()
     }
 
     pub fn get_root_page(&self) -> usize {
         self.root_page
     }
+
+    pub fn get_table_type(&self) -> &TableType {
+        &self.table_type
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_tablename(&self) -> &str {
+        &self.tablename
+    }
 }
@@ -1,18 +1,35 @@
 //! Api to execute a raw sql string or a Sqlite special command
 use crate::db::DB;
-use crate::db::fileformat::page::Page;
-use crate::db::fileformat::record::Record;
-use crate::db::table::Table;
+use crate::db::cursor::{IndexCursor, TableCursor, compare_values};
+use crate::db::fileformat::{header::TextEncoding, record::Record};
+use crate::db::table::{Definition, Table};
+use crate::executor::aggregate::{AggregateValues, Grouping};
+use crate::executor::collation::Collation;
 use crate::executor::db_response::{RType, Response};
-use crate::parser::function::FuncCall;
-use crate::parser::identifier::{Identifier, VType};
-use crate::parser::select::{SelectClause, SelectItem};
+use crate::executor::eval::{
+    apply_comparison_affinity, comparison_affinity, evaluate, evaluate_constant, is_true,
+};
+use crate::executor::join::{ColumnResolver, JoinLevel, JoinSource};
+use crate::parser::create_table::Affinity;
+use crate::parser::create_view::CreateViewStatement;
+use crate::parser::expr::{BinaryOperator, Expr};
+use crate::parser::from_clause::FromClause;
+use crate::parser::order_by::OrderingTerm;
+use crate::parser::select::{Limit, SelectClause, SelectItem};
 use crate::parser::token::Command;
 use crate::parser::where_clause::Where;
 use crate::parser::{Parser, select::SelectStatement, statement::Statement};
 use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::rc::Rc;
 
+pub mod aggregate;
+pub mod collation;
 pub mod db_response;
+pub mod eval;
+pub mod join;
+pub mod pattern;
 
 pub struct Executor {
     db: DB,
@@ -27,6 +44,7 @@ impl Executor {
     /// There are two types of command:
     /// * special commands: .dbinfo, .tables
     /// * a SQL query.
+    ///
     /// Returns None for special commands, or Some(Vec<(Query, Response)) for SQL queries.
     /// Response is a Vec<Vec<[Rtype](crate::executor::db_response)>>
     pub fn execute(&mut self, command: &str) -> Result<Vec<(Statement, Response)>> {
@@ -46,100 +64,1240 @@ impl Executor {
     fn execute_query(&mut self, query: &Statement) -> Result<Option<Response>> {
         match query {
             Statement::Select(select_statement) => self.execute_select_statement(select_statement),
+            Statement::CreateTable(_) | Statement::CreateIndex(_) | Statement::CreateView(_) => {
+                Err(anyhow!("Executor: the database is read-only"))
+            }
             Statement::Command(Command::DBinfo) => self.db.metadata.get_metadata(),
             Statement::Command(Command::Tables) => self.db.metadata.get_metadata(),
         }
     }
 
+    fn execute_select_statement(&mut self, query: &SelectStatement) -> Result<Option<Response>> {
+        Ok(self.select(query)?.map(|(_, response)| response))
+    }
+
     // NOTE: about lifetime here.
     // Table has to live longer than each indivual records. Each records contains a
     // HashMap of columns. Keys are &str to the table definition column's name.
     // Records and Table lives only in this function scope.
-    fn execute_select_statement(&mut self, query: &SelectStatement) -> Result<Option<Response>> {
-        let Some(table) = self.db.take_table(&query.from_clause) else {
-            return Ok(None);
+    //
+    // Returns the result column names with the rows.
+    fn select(&mut self, query: &SelectStatement) -> Result<Option<(Vec<String>, Response)>> {
+        let sources = match &query.from_clause {
+            Some(from_clause) => match self.get_join_sources(from_clause)? {
+                Some(sources) => sources,
+                None => return Ok(None),
+            },
+            None => vec![get_constant_source(query)?],
+        };
+        // Column references are resolved to the columns of the records that are read
+        let resolver = ColumnResolver::new(&sources)?;
+        let query = &resolver.resolve_query(query)?;
+        let conditions = resolver.into_conditions();
+        // Where clause of the first table of a join, on the names of its own columns
+        let outer_where;
+        let (table, plan) = if sources.len() == 1 {
+            let source = sources.into_iter().next().expect("There is one table");
+            let plan = match source.rows {
+                Some(rows) => ScanPlan::Rows(rows),
+                None => self.plan_scan(query.where_clause.as_ref(), &source.table),
+            };
+            (source.table, plan)
+        } else {
+            let tables = sources
+                .iter()
+                .map(|source| (source.qualifier.as_str(), source.table.clone()))
+                .collect::<Vec<_>>();
+            let table = Rc::new(Table::from_join(&tables));
+            let mut sources = sources.into_iter();
+            let outer = sources.next().expect("A join has several tables");
+            outer_where = query
+                .where_clause
+                .as_ref()
+                .map(|where_clause| join::get_outer_where(where_clause, &outer.qualifier));
+            let outer_plan = match outer.rows {
+                Some(rows) => ScanPlan::Rows(rows),
+                None => self.plan_scan(outer_where.as_ref(), &outer.table),
+            };
+            let levels = join::plan_join(
+                &self.db,
+                sources.collect(),
+                conditions.into_iter().skip(1).collect(),
+                query.where_clause.as_ref(),
+                &table,
+                outer.table.cols_name.len(),
+            )?;
+            let plan = ScanPlan::Join {
+                outer: Box::new(outer_plan),
+                outer_table: outer.table,
+                levels,
+            };
+            (table, plan)
         };
+        let aggregates = query.get_aggregates();
+        let is_aggregate = query.is_aggregate();
+        if query.having.is_some() && !is_aggregate {
+            return Err(anyhow!("HAVING clause on a non-aggregate query"));
+        }
+        // HAVING can name result columns by their alias, like GROUP BY
+        let having = query
+            .having
+            .as_ref()
+            .map(|having| resolve_aliases(having, &query.select_clause, &table));
+        let columns = get_result_columns(&query.select_clause, &table);
+        let sort_keys = get_sort_keys(&query.order_by, &query.select_clause, &table)?;
 
-        let page = self.db.get_page(table.get_root_page())?;
-        let records = page.get_all_records(&table)?;
-        let response = records
-            .into_iter()
-            .filter(|record| {
-                if let Some(where_clause) = &query.where_clause {
-                    apply_where_clause(record, &where_clause)
-                } else {
-                    true
-                }
-            })
-            .map(|record| apply_select_clause(record, &query.select_clause, &table))
-            .collect::<Result<Response>>()?;
-
-        if let Some(func) = query.select_clause.get_function() {
-            Ok(Some(vec![execute_function(&page, func)]))
+        // Groups are in the order of their first row, so they are ordered like the scan
+        let needs_sort = !is_scan_ordered(&plan, &table, &sort_keys);
+        let mut grouping = if is_aggregate {
+            let keys = get_group_keys(query, &table)?;
+            let is_ordered = is_grouping_ordered(&plan, &table, &keys);
+            Some(Grouping::new(keys, &aggregates, is_ordered, &table)?)
+        } else {
+            None
+        };
+        let (offset, limit) = get_offset_and_limit(query.limit.as_ref())?;
+        // The scan stops once it has the rows to return, it cannot when they are sorted
+        let scan_limit = match limit {
+            Some(limit) if !needs_sort && !is_aggregate => Some(offset.saturating_add(limit)),
+            _ => None,
+        };
+
+        let mut distinct_rows = if query.select_clause.distinct {
+            Some(DistinctRows::new(&query.select_clause, &table)?)
         } else {
-            Ok(Some(response))
+            None
+        };
+        let encoding = self.db.text_encoding;
+        let mut response = vec![];
+        // ORDER BY values of each row of the response
+        let mut sort_values = vec![];
+        // Returns false when the scan can stop
+        let mut process = |record: Record| -> Result<bool> {
+            if scan_limit.is_some_and(|scan_limit| response.len() >= scan_limit) {
+                return Ok(false);
+            }
+            if let Some(where_clause) = &query.where_clause
+                && !is_true(&evaluate(&where_clause.expr, &record)?)
+            {
+                return Ok(true);
+            }
+            if let Some(grouping) = &mut grouping {
+                grouping.add(&record)?;
+                return Ok(true);
+            }
+            let row = apply_select_clause(&record, &query.select_clause, &table, None)?;
+            if distinct_rows
+                .as_mut()
+                .is_some_and(|distinct_rows| !distinct_rows.insert(&row))
+            {
+                return Ok(true);
+            }
+            if needs_sort {
+                sort_values.push(get_sort_values(&sort_keys, &record, &row, None)?);
+            }
+            response.push(row);
+            Ok(scan_limit.is_none_or(|scan_limit| response.len() < scan_limit))
+        };
+
+        self.scan(plan, &table, &mut process)?;
+
+        if let Some(grouping) = grouping {
+            for group in grouping.finish(table.cols_name.len()) {
+                let (row, values) = group.finish(&aggregates)?;
+                let record = Record::from_values(row, &table, encoding);
+                if let Some(having) = &having
+                    && !is_true(&values.evaluate(having, &record)?)
+                {
+                    continue;
+                }
+                let row =
+                    apply_select_clause(&record, &query.select_clause, &table, Some(&values))?;
+                if distinct_rows
+                    .as_mut()
+                    .is_some_and(|distinct_rows| !distinct_rows.insert(&row))
+                {
+                    continue;
+                }
+                if needs_sort {
+                    sort_values.push(get_sort_values(&sort_keys, &record, &row, Some(&values))?);
+                }
+                response.push(row);
+            }
+        }
+        if needs_sort {
+            response = sort_rows(response, sort_values, &sort_keys, encoding);
         }
+        let response = response
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok(Some((columns, response)))
+    }
+
+    /// Read the records of the table with the plan, `process` returns false when
+    /// the scan can stop
+    fn scan<'t>(
+        &self,
+        plan: ScanPlan,
+        table: &'t Table,
+        process: &mut dyn FnMut(Record<'t>) -> Result<bool>,
+    ) -> Result<()> {
+        let encoding = self.db.text_encoding;
+        match plan {
+            ScanPlan::Join {
+                outer,
+                outer_table,
+                levels,
+            } => {
+                self.scan(*outer, &outer_table, &mut |record| {
+                    join::scan(&self.db, &levels, table, record.get_values(), process)
+                })?;
+            }
+            ScanPlan::Rows(rows) => {
+                for row in rows {
+                    if !process(Record::from_values(row, table, encoding))? {
+                        break;
+                    }
+                }
+            }
+            ScanPlan::IndexLookup {
+                index_root, values, ..
+            } => {
+                let mut index_cursor = IndexCursor::new(&self.db, index_root)?;
+                let mut rowids = vec![];
+                for value in values {
+                    rowids.extend(index_cursor.find_rowids(&value)?);
+                }
+                let mut cursor = TableCursor::new(&self.db, table.get_root_page())?;
+                for rowid in rowids {
+                    if let Some(record) = cursor.find(rowid, table)?
+                        && !process(record)?
+                    {
+                        break;
+                    }
+                }
+            }
+            ScanPlan::RowidRange { start, end } => {
+                let mut cursor = TableCursor::new(&self.db, table.get_root_page())?;
+                if let Some(start) = start {
+                    cursor.seek(start)?;
+                }
+                while let Some(record) = cursor.next_record(table)? {
+                    if end.is_some_and(|end| record.rowid as i64 > end) {
+                        break;
+                    }
+                    if !process(record)? {
+                        break;
+                    }
+                }
+            }
+            ScanPlan::PrimaryKeyRange { column, start, end } => {
+                let mut cursor = IndexCursor::new(&self.db, table.get_root_page())?;
+                if let Some(start) = start {
+                    cursor.seek(&[start])?;
+                }
+                while let Some(record) = cursor.next_record(table)? {
+                    if let Some(end) = &end
+                        && compare_values(record.get_column_value(column), end, encoding)
+                            == Ordering::Greater
+                    {
+                        break;
+                    }
+                    if !process(record)? {
+                        break;
+                    }
+                }
+            }
+            ScanPlan::FullScan if table.is_without_rowid() => {
+                let mut cursor = IndexCursor::new(&self.db, table.get_root_page())?;
+                while let Some(record) = cursor.next_record(table)? {
+                    if !process(record)? {
+                        break;
+                    }
+                }
+            }
+            ScanPlan::FullScan => {
+                let mut cursor = TableCursor::new(&self.db, table.get_root_page())?;
+                while let Some(record) = cursor.next_record(table)? {
+                    if !process(record)? {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tables of the FROM clause, the rows of views are computed first.
+    /// Returns None when the first table does not exist.
+    fn get_join_sources(&mut self, from_clause: &FromClause) -> Result<Option<Vec<JoinSource>>> {
+        let mut sources = vec![];
+        for (i, table_ref) in from_clause.get_tables().enumerate() {
+            let Some(table) = self.db.get_table(&table_ref.name) else {
+                if i == 0 {
+                    return Ok(None);
+                }
+                return Err(anyhow!("no such table: {}", table_ref.name));
+            };
+            table.check_definition()?;
+            // Views are expanded as subqueries: their rows are computed first,
+            // then read like the rows of a table.
+            let (table, rows) = if let Definition::View(create_view) = table.get_definition() {
+                let (cols_name, rows) = self.expand_view(create_view)?;
+                let view_table = Table::from_view_columns(table.get_name(), cols_name);
+                (Rc::new(view_table), Some(rows))
+            } else {
+                (table, None)
+            };
+            sources.push(JoinSource {
+                qualifier: table_ref.get_qualifier().to_string(),
+                table,
+                rows,
+                join: i.checked_sub(1).map(|i| from_clause.joins[i].clone()),
+            });
+        }
+        Ok(Some(sources))
+    }
+
+    /// Run the select of a view.
+    /// The view column list, when there is one, renames the select columns.
+    fn expand_view(
+        &mut self,
+        create_view: &CreateViewStatement,
+    ) -> Result<(Vec<String>, Response)> {
+        let Some((cols_name, rows)) = self.select(&create_view.select)? else {
+            let from_clause = create_view.select.from_clause.as_ref();
+            return Err(anyhow!(
+                "View {}: no such table: {}",
+                create_view.name,
+                from_clause.map_or("", |from_clause| from_clause.table.name.as_str())
+            ));
+        };
+        if create_view.columns.is_empty() {
+            return Ok((cols_name, rows));
+        }
+        if create_view.columns.len() != cols_name.len() {
+            return Err(anyhow!(
+                "View {}: expected {} columns but got {}",
+                create_view.name,
+                create_view.columns.len(),
+                cols_name.len()
+            ));
+        }
+        Ok((create_view.columns.clone(), rows))
+    }
+
+    /// Choose how to read the table records from the where clause:
+    /// * a comparison on the rowid or its alias seeks directly in the table B-tree
+    /// * a comparison on the first primary key column of a WITHOUT ROWID table
+    ///   seeks in the table B-tree as well
+    /// * an equality or an IN list on the first column of an index walks the index B-tree
+    /// * anything else scans the whole table
+    ///
+    /// With several conditions joined by AND, the first one that avoids a full scan is used,
+    /// the whole where clause is still checked on every record.
+    fn plan_scan<'q>(&self, where_clause: Option<&'q Where>, table: &Table) -> ScanPlan<'q> {
+        let Some(where_clause) = where_clause else {
+            return ScanPlan::FullScan;
+        };
+        for (column, operator, value) in where_clause.get_comparisons() {
+            let plan = self.plan_comparison(table, column, operator, value);
+            if !matches!(plan, ScanPlan::FullScan) {
+                return plan;
+            }
+        }
+        for (column, values) in where_clause.get_in_lists() {
+            let plan = self.plan_in_list(table, column, values);
+            if !matches!(plan, ScanPlan::FullScan) {
+                return plan;
+            }
+        }
+        ScanPlan::FullScan
+    }
+
+    fn plan_comparison<'q>(
+        &self,
+        table: &Table,
+        column: &'q str,
+        operator: BinaryOperator,
+        value: RType,
+    ) -> ScanPlan<'q> {
+        // The value is converted like the evaluator does, so `id = '5'` seeks the rowid 5
+        let affinity = table.find_column_affinity(column);
+        let value = apply_comparison_affinity(value, comparison_affinity(affinity, None));
+        if table.get_primary_key_seek_column() == Some(column) {
+            return match operator {
+                BinaryOperator::Eq => ScanPlan::PrimaryKeyRange {
+                    column,
+                    start: Some(value.clone()),
+                    end: Some(value),
+                },
+                BinaryOperator::GT | BinaryOperator::GTE => ScanPlan::PrimaryKeyRange {
+                    column,
+                    start: Some(value),
+                    end: None,
+                },
+                BinaryOperator::LT | BinaryOperator::LTE => ScanPlan::PrimaryKeyRange {
+                    column,
+                    start: None,
+                    end: Some(value),
+                },
+                _ => ScanPlan::FullScan,
+            };
+        }
+        // Index entries of WITHOUT ROWID tables hold the primary key instead of the rowid
+        if table.is_without_rowid() {
+            return ScanPlan::FullScan;
+        }
+
+        if table.is_rowid_column(column) {
+            let RType::Num(rowid) = value else {
+                return ScanPlan::FullScan;
+            };
+            return match operator {
+                BinaryOperator::Eq => ScanPlan::RowidRange {
+                    start: Some(rowid),
+                    end: Some(rowid),
+                },
+                BinaryOperator::GT => ScanPlan::RowidRange {
+                    start: rowid.checked_add(1),
+                    end: None,
+                },
+                BinaryOperator::GTE => ScanPlan::RowidRange {
+                    start: Some(rowid),
+                    end: None,
+                },
+                BinaryOperator::LT => ScanPlan::RowidRange {
+                    start: None,
+                    end: rowid.checked_sub(1),
+                },
+                BinaryOperator::LTE => ScanPlan::RowidRange {
+                    start: None,
+                    end: Some(rowid),
+                },
+                _ => ScanPlan::FullScan,
+            };
+        }
+
+        if operator == BinaryOperator::Eq
+            && let Some(index_root) = self.db.metadata.find_index(table.get_name(), column)
+        {
+            return ScanPlan::IndexLookup {
+                index_root,
+                column,
+                values: vec![value],
+            };
+        }
+        ScanPlan::FullScan
+    }
+
+    // An IN list on the first column of an index is answered by one index seek per value
+    fn plan_in_list<'q>(&self, table: &Table, column: &'q str, values: Vec<RType>) -> ScanPlan<'q> {
+        if table.is_without_rowid() {
+            return ScanPlan::FullScan;
+        }
+        let Some(index_root) = self.db.metadata.find_index(table.get_name(), column) else {
+            return ScanPlan::FullScan;
+        };
+        let affinity = table
+            .get_column_index(column)
+            .map(|index| table.get_column_affinity(index));
+        let affinity = comparison_affinity(affinity, None);
+        // Sorted values without duplicates read each matching row once, in index order.
+        // NULL never matches.
+        let mut values = values
+            .into_iter()
+            .filter(|value| !matches!(value, RType::Null))
+            .map(|value| apply_comparison_affinity(value, affinity))
+            .collect::<Vec<_>>();
+        values.sort_by(|left, right| compare_values(left, right, self.db.text_encoding));
+        values.dedup();
+        ScanPlan::IndexLookup {
+            index_root,
+            column,
+            values,
+        }
+    }
+}
+
+/// How the records of a table are read
+enum ScanPlan<'q> {
+    FullScan,
+    // Rows already computed by a subquery, like the rows of a view
+    Rows(Response),
+    // Nested loops over the tables of a join, the rows of the first table are read
+    // with their own plan and joined one by one to the rows of the other tables
+    Join {
+        outer: Box<ScanPlan<'q>>,
+        outer_table: Rc<Table>,
+        levels: Vec<JoinLevel>,
+    },
+    // Inclusive rowid bounds, None means unbounded
+    RowidRange {
+        start: Option<i64>,
+        end: Option<i64>,
+    },
+    // Rows whose indexed column is equal to one of the values,
+    // they are read in the order of the sorted values
+    IndexLookup {
+        index_root: usize,
+        column: &'q str,
+        values: Vec<RType>,
+    },
+    // Inclusive bounds on the first primary key column of a WITHOUT ROWID table
+    PrimaryKeyRange {
+        column: &'q str,
+        start: Option<RType>,
+        end: Option<RType>,
+    },
+}
+
+// How the rows are compared for one ORDER BY term
+struct SortKey<'q> {
+    term: &'q OrderingTerm,
+    // Expression of the term, a column number or an alias is replaced by the
+    // expression of the result column
+    expr: Expr,
+    // Index of the result column when the term is a column number or an alias
+    position: Option<usize>,
+    collation: Collation,
+}
+
+// Rows already returned by a SELECT DISTINCT. The values of each column are
+// compared with its collation, NULLs are equal and so are 1 and 1.0.
+struct DistinctRows {
+    collations: Vec<Collation>,
+    seen: HashSet<Vec<RType>>,
+}
+
+impl DistinctRows {
+    fn new(select_clause: &SelectClause, table: &Table) -> Result<Self> {
+        let mut collations = vec![];
+        for item in select_clause.items.iter() {
+            match item {
+                SelectItem::Star | SelectItem::TableStar(_) => {
+                    for col_name in table.cols_name.iter() {
+                        let column = Expr::Column(col_name.clone());
+                        collations.push(Collation::from_expr(&column, table)?);
+                    }
+                }
+                SelectItem::Expr { expr, .. } => {
+                    collations.push(Collation::from_expr(expr, table)?)
+                }
+            }
+        }
+        Ok(Self {
+            collations,
+            seen: HashSet::new(),
+        })
+    }
+
+    // Returns true if the row was not seen before
+    fn insert(&mut self, row: &[RType]) -> bool {
+        let key = row
+            .iter()
+            .zip(&self.collations)
+            .map(|(value, collation)| collation.normalize(value.clone()))
+            .collect();
+        self.seen.insert(key)
     }
 }
 
-fn execute_function(page: &Page, func: &FuncCall) -> Vec<RType> {
-    match func.function_name.as_str() {
-        "count" => vec![RType::Num(page.get_record_number() as i64)],
-        _ => vec![],
+// Without FROM clause, the select expressions are evaluated once,
+// on a row without columns
+fn get_constant_source(query: &SelectStatement) -> Result<JoinSource> {
+    if query
+        .select_clause
+        .items
+        .iter()
+        .any(|item| matches!(item, SelectItem::Star | SelectItem::TableStar(_)))
+    {
+        return Err(anyhow!("no tables specified"));
     }
+    Ok(JoinSource {
+        qualifier: String::new(),
+        table: Rc::new(Table::from_view_columns("", vec![])),
+        rows: Some(vec![vec![]]),
+        join: None,
+    })
 }
 
-fn apply_where_clause(record: &Record, where_clause: &Where) -> bool {
-    // For now, we assume there is only one identifier in the where clause
-    if let Some(identifier) = where_clause.get_identifier() {
-        let identifier_value = record.get_column_value(identifier);
-        return where_clause.evaluate(Some(identifier_value));
+// Rows to skip and maximum number of rows to return, None when there is no limit.
+// A negative limit is no limit and a negative offset is 0.
+fn get_offset_and_limit(limit: Option<&Limit>) -> Result<(usize, Option<usize>)> {
+    let Some(limit) = limit else {
+        return Ok((0, None));
+    };
+    let offset = match &limit.offset {
+        Some(offset) => get_limit_value(offset)?.max(0) as usize,
+        None => 0,
     };
-    where_clause.evaluate(None)
+    let limit = usize::try_from(get_limit_value(&limit.limit)?).ok();
+    Ok((offset, limit))
 }
 
-fn apply_select_clause(
-    mut record: Record,
-    select: &SelectClause,
+fn get_limit_value(expr: &Expr) -> Result<i64> {
+    let value = evaluate_constant(expr)?;
+    match apply_comparison_affinity(value, Some(Affinity::Integer)) {
+        RType::Num(value) => Ok(value),
+        _ => Err(anyhow!("datatype mismatch")),
+    }
+}
+
+// An alias of a result column in an ORDER BY term hides the table column with the
+// same name, aliases in expressions are replaced by the expression they name.
+fn get_sort_keys<'q>(
+    order_by: &'q [OrderingTerm],
+    select_clause: &SelectClause,
     table: &Table,
+) -> Result<Vec<SortKey<'q>>> {
+    let result_exprs = get_result_exprs(select_clause, table);
+    let mut sort_keys = vec![];
+    for (i, term) in order_by.iter().enumerate() {
+        let position = match &term.expr {
+            Expr::Literal(RType::Num(position)) => {
+                if *position < 1 || *position as usize > result_exprs.len() {
+                    return Err(anyhow!(
+                        "{} ORDER BY term out of range - should be between 1 and {}",
+                        ordinal(i + 1),
+                        result_exprs.len()
+                    ));
+                }
+                Some(*position as usize - 1)
+            }
+            Expr::Column(name) => get_alias_position(select_clause, table, name),
+            _ => None,
+        };
+        let expr = match position {
+            Some(position) => result_exprs[position].clone(),
+            None => resolve_aliases(&term.expr, select_clause, table),
+        };
+        // A column is sorted with its declared collation, unless the term has one
+        let collation = match &term.collation {
+            Some(collation) => Collation::from_name(collation)?,
+            None => Collation::from_expr(&expr, table)?,
+        };
+        sort_keys.push(SortKey {
+            term,
+            expr,
+            position,
+            collation,
+        });
+    }
+    Ok(sort_keys)
+}
+
+// Expressions of the result columns, `*` is replaced by the columns of the table.
+// With a single table, `table.*` is the same as `*`.
+fn get_result_exprs(select_clause: &SelectClause, table: &Table) -> Vec<Expr> {
+    select_clause
+        .items
+        .iter()
+        .flat_map(|item| match item {
+            SelectItem::Star | SelectItem::TableStar(_) => table
+                .cols_name
+                .iter()
+                .map(|col_name| Expr::Column(col_name.clone()))
+                .collect(),
+            SelectItem::Expr { expr, .. } => vec![expr.clone()],
+        })
+        .collect()
+}
+
+// Position of the result column named `name` with AS
+fn get_alias_position(select_clause: &SelectClause, table: &Table, name: &str) -> Option<usize> {
+    let mut position = 0;
+    for item in select_clause.items.iter() {
+        match item {
+            SelectItem::Star | SelectItem::TableStar(_) => position += table.cols_name.len(),
+            SelectItem::Expr {
+                alias: Some(alias), ..
+            } if alias.eq_ignore_ascii_case(name) => return Some(position),
+            SelectItem::Expr { .. } => position += 1,
+        }
+    }
+    None
+}
+
+// Replace the aliases of result columns by the expression they name,
+// the columns of the table hide the aliases
+fn resolve_aliases(expr: &Expr, select_clause: &SelectClause, table: &Table) -> Expr {
+    expr.transform(&|expr| {
+        let Expr::Column(name) = expr else {
+            return None;
+        };
+        if table.get_column_index(name).is_some() {
+            return None;
+        }
+        select_clause.items.iter().find_map(|item| match item {
+            SelectItem::Expr {
+                expr,
+                alias: Some(alias),
+            } if alias.eq_ignore_ascii_case(name) => Some(expr.clone()),
+            _ => None,
+        })
+    })
+}
+
+// GROUP BY expressions with the collation comparing their values.
+// An integer literal is replaced by the result column at this position.
+fn get_group_keys(query: &SelectStatement, table: &Table) -> Result<Vec<(Expr, Collation)>> {
+    let result_exprs = get_result_exprs(&query.select_clause, table);
+    let mut keys = vec![];
+    for (i, expr) in query.group_by.iter().enumerate() {
+        let expr = match expr {
+            Expr::Literal(RType::Num(position)) => {
+                if *position < 1 || *position as usize > result_exprs.len() {
+                    return Err(anyhow!(
+                        "{} GROUP BY term out of range - should be between 1 and {}",
+                        ordinal(i + 1),
+                        result_exprs.len()
+                    ));
+                }
+                result_exprs[*position as usize - 1].clone()
+            }
+            expr => resolve_aliases(expr, &query.select_clause, table),
+        };
+        if expr.contains_aggregate() {
+            return Err(anyhow!(
+                "aggregate functions are not allowed in the GROUP BY clause"
+            ));
+        }
+        let collation = Collation::from_expr(&expr, table)?;
+        keys.push((expr, collation));
+    }
+    Ok(keys)
+}
+
+fn ordinal(number: usize) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (1, 11) | (2, 12) | (3, 13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+// True when the scan returns the rows in the ORDER BY order, in ascending order of:
+// * the rowid for rowid tables
+// * the primary key for WITHOUT ROWID tables
+// * the indexed column for index lookups
+fn is_scan_ordered(plan: &ScanPlan, table: &Table, sort_keys: &[SortKey]) -> bool {
+    let Some(first) = sort_keys.first() else {
+        return true;
+    };
+    let Expr::Column(col_name) = &first.expr else {
+        return false;
+    };
+    if first.term.is_descending()
+        || !first.term.nulls_first()
+        || first.collation != Collation::Binary
+    {
+        return false;
+    }
+    // Rows with the same value of a column that is not unique can be in any order
+    get_scan_order(plan, table).is_some_and(|(column, is_unique)| {
+        column == col_name && (is_unique || sort_keys.len() == 1)
+    })
+}
+
+// True when the rows of a group are consecutive in the scan
+fn is_grouping_ordered(plan: &ScanPlan, table: &Table, keys: &[(Expr, Collation)]) -> bool {
+    let Some((first, collation)) = keys.first() else {
+        return true;
+    };
+    let Expr::Column(col_name) = first else {
+        return false;
+    };
+    *collation == Collation::Binary
+        && get_scan_order(plan, table)
+            .is_some_and(|(column, is_unique)| column == col_name && (is_unique || keys.len() == 1))
+}
+
+// The column ordering the rows of the scan, and whether its values are unique
+fn get_scan_order<'a>(plan: &'a ScanPlan, table: &'a Table) -> Option<(&'a str, bool)> {
+    match plan {
+        ScanPlan::Rows(_) | ScanPlan::Join { .. } => None,
+        ScanPlan::IndexLookup { column, .. } => Some((*column, false)),
+        _ if table.is_without_rowid() => table
+            .get_primary_key_seek_column()
+            .map(|column| (column, table.get_primary_key_len() == 1)),
+        _ => table.get_rowid_alias().map(|column| (column, true)),
+    }
+}
+
+fn get_sort_values(
+    sort_keys: &[SortKey],
+    record: &Record,
+    row: &[RType],
+    aggregates: Option<&AggregateValues>,
 ) -> Result<Vec<RType>> {
-    let mut selected_row = vec![];
-    let col_names = get_selected_colname(select, table);
+    sort_keys
+        .iter()
+        .map(|key| match key.position {
+            Some(position) => Ok(row[position].clone()),
+            None => evaluate_with_aggregates(&key.expr, record, aggregates),
+        })
+        .collect()
+}
+
+// Stable sort: rows with equal ORDER BY values keep the scan order
+fn sort_rows(
+    response: Response,
+    sort_values: Vec<Vec<RType>>,
+    sort_keys: &[SortKey],
+    encoding: TextEncoding,
+) -> Response {
+    let mut rows = sort_values.into_iter().zip(response).collect::<Vec<_>>();
+    rows.sort_by(|(left, _), (right, _)| compare_sort_values(sort_keys, left, right, encoding));
+    rows.into_iter().map(|(_, row)| row).collect()
+}
 
-    for col_name in col_names {
-        if let Some(field) = record.take_field(col_name) {
-            selected_row.push(field)
+fn compare_sort_values(
+    sort_keys: &[SortKey],
+    left: &[RType],
+    right: &[RType],
+    encoding: TextEncoding,
+) -> Ordering {
+    for (key, (left, right)) in sort_keys.iter().zip(left.iter().zip(right)) {
+        let null_ordering = if key.term.nulls_first() {
+            Ordering::Less
         } else {
-            return Err(anyhow!("Select clause: invalid columna name: {}", col_name));
+            Ordering::Greater
+        };
+        let ordering = match (left, right) {
+            (RType::Null, RType::Null) => Ordering::Equal,
+            (RType::Null, _) => null_ordering,
+            (_, RType::Null) => null_ordering.reverse(),
+            _ if key.term.is_descending() => key.collation.compare(left, right, encoding).reverse(),
+            _ => key.collation.compare(left, right, encoding),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
         }
     }
-    Ok(selected_row)
+    Ordering::Equal
 }
 
-fn get_selected_colname<'a>(select_clause: &'a SelectClause, table: &'a Table) -> Vec<&'a str> {
-    let mut col_indexes = vec![];
-    if select_clause.items.len() == 0 {
-        return col_indexes;
+// Evaluate an expression for a row, or for a group of an aggregate query
+fn evaluate_with_aggregates(
+    expr: &Expr,
+    record: &Record,
+    aggregates: Option<&AggregateValues>,
+) -> Result<RType> {
+    match aggregates {
+        Some(aggregates) => aggregates.evaluate(expr, record),
+        None => evaluate(expr, record),
     }
+}
 
+fn apply_select_clause(
+    record: &Record,
+    select: &SelectClause,
+    table: &Table,
+    aggregates: Option<&AggregateValues>,
+) -> Result<Vec<RType>> {
+    let mut selected_row = vec![];
+    for item in select.items.iter() {
+        match item {
+            SelectItem::Star | SelectItem::TableStar(_) => {
+                for col_name in table.cols_name.iter() {
+                    selected_row.push(evaluate(&Expr::Column(col_name.clone()), record)?);
+                }
+            }
+            SelectItem::Expr { expr, .. } => {
+                selected_row.push(evaluate_with_aggregates(expr, record, aggregates)?)
+            }
+        }
+    }
+    Ok(selected_row)
+}
+
+// Names of the result columns: the alias when there is one, the column name
+// for a column reference, the expression text otherwise
+fn get_result_columns(select_clause: &SelectClause, table: &Table) -> Vec<String> {
+    let mut columns = vec![];
     for item in select_clause.items.iter() {
         match item {
-            SelectItem::Identifier(Identifier {
-                value: VType::Str(col_name),
-            }) => {
-                col_indexes.push(col_name);
+            SelectItem::Star | SelectItem::TableStar(_) => {
+                columns.extend(table.cols_name.iter().cloned())
             }
-            SelectItem::Star => {
-                return table
-                    .cols_name
-                    .iter()
-                    .map(|str| str.as_ref())
-                    .collect::<Vec<&'a str>>();
+            SelectItem::Expr {
+                alias: Some(alias), ..
+            } => columns.push(alias.clone()),
+            // The columns of a join are named `table.column`
+            SelectItem::Expr {
+                expr: Expr::Column(col_name),
+                ..
+            } => columns.push(
+                col_name
+                    .split_once('.')
+                    .map_or(col_name.as_str(), |(_, col_name)| col_name)
+                    .to_string(),
+            ),
+            SelectItem::Expr { expr, .. } => columns.push(expr.to_string()),
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Open a database of tests/fixtures, built by create_fixtures.sh
+    fn open(database: &str) -> Executor {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), database);
+        Executor::new(DB::new(&path).unwrap())
+    }
+
+    fn query(database: &str, sql: &str) -> Response {
+        let mut responses = open(database).execute(sql).unwrap();
+        responses.pop().unwrap().1
+    }
+
+    #[test]
+    fn it_should_read_the_rowid_pseudo_columns() {
+        assert_eq!(
+            query(
+                "rowids.db",
+                "SELECT rowid, oid, _rowid_, name FROM items WHERE rowid = 30"
+            ),
+            vec![vec![
+                RType::Num(30),
+                RType::Num(30),
+                RType::Num(30),
+                RType::Str("item10".to_string()),
+            ]]
+        );
+        assert_eq!(
+            query("rowids.db", "SELECT count(*) FROM items WHERE oid >= 1200"),
+            vec![vec![RType::Num(101)]]
+        );
+        // Columns named like the rowid hide it
+        assert_eq!(
+            query("rowids.db", "SELECT rowid FROM shadowed WHERE oid = 20"),
+            vec![vec![RType::Str("second".to_string())]]
+        );
+        let db = DB::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/rowids.db"
+        ));
+        let mut executor = Executor::new(db.unwrap());
+        assert!(executor.execute("SELECT rowid FROM keyed").is_err());
+    }
+
+    #[test]
+    fn it_should_plan_rowid_ranges_on_the_rowid_pseudo_columns() {
+        let db = DB::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/rowids.db"
+        ));
+        let executor = Executor::new(db.unwrap());
+        let table = executor.db.get_table("items").unwrap();
+        let plan = |sql: &str| {
+            let Some(Ok(Statement::Select(select))) = Parser::new(sql).next() else {
+                panic!("Expected a SELECT statement");
+            };
+            match executor.plan_scan(select.where_clause.as_ref(), &table) {
+                ScanPlan::RowidRange { start, end } => Some((start, end)),
+                _ => None,
             }
-            SelectItem::Function(_) => {}
-            _ => panic!("Should always be a valid select item: {:?}", item),
+        };
+        assert_eq!(
+            plan("SELECT * FROM items WHERE rowid = 5"),
+            Some((Some(5), Some(5)))
+        );
+        assert_eq!(
+            plan("SELECT * FROM items WHERE oid > 5"),
+            Some((Some(6), None))
+        );
+        assert_eq!(
+            plan("SELECT * FROM items WHERE _rowid_ <= '5'"),
+            Some((None, Some(5)))
+        );
+    }
+
+    #[test]
+    fn it_should_order_text_in_the_database_encoding() {
+        let words = |sql| {
+            query("utf16.db", sql)
+                .into_iter()
+                .map(|row| row[0].to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            words("SELECT s FROM words ORDER BY s"),
+            vec!["Ā", "B", "a", "b", "c", "z"]
+        );
+        assert_eq!(
+            words("SELECT s FROM words WHERE s > 'b' ORDER BY n"),
+            vec!["c", "z"]
+        );
+        assert_eq!(words("SELECT min(s) FROM words"), vec!["Ā"]);
+    }
+
+    #[test]
+    fn it_should_report_invalid_tables_and_views_when_queried() {
+        assert_eq!(
+            query("unsupported.db", "SELECT a FROM good"),
+            vec![vec![RType::Num(1)], vec![RType::Num(2)]]
+        );
+        let error = open("unsupported.db")
+            .execute("SELECT * FROM odd")
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Schema: invalid definition for odd")
+        );
+        // Views using an unsupported select fail when they are queried
+        assert_eq!(
+            query("unsupported.db", "SELECT * FROM plain"),
+            vec![vec![RType::Num(2)]]
+        );
+        for view in ["cases", "unions", "casts", "nested"] {
+            let error = open("unsupported.db")
+                .execute(&format!("SELECT * FROM {}", view))
+                .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .starts_with(&format!("Schema: invalid definition for {}", view))
+            );
         }
     }
 
-    col_indexes
+    #[test]
+    fn it_should_evaluate_a_select_without_from_as_one_row() {
+        assert_eq!(
+            query("unsupported.db", "SELECT 1 + 2, 'a' || 'b'"),
+            vec![vec![RType::Num(3), RType::Str("ab".to_string())]]
+        );
+        assert_eq!(
+            query("unsupported.db", "SELECT 1 WHERE 0"),
+            Vec::<Vec<RType>>::new()
+        );
+        assert_eq!(
+            query("unsupported.db", "SELECT count(*)"),
+            vec![vec![RType::Num(1)]]
+        );
+        let error = open("unsupported.db").execute("SELECT *").unwrap_err();
+        assert_eq!(error.to_string(), "no tables specified");
+    }
+
+    #[test]
+    fn it_should_read_integers_out_of_range_as_reals() {
+        let row = query(
+            "unsupported.db",
+            "SELECT 9223372036854775808, -9223372036854775808",
+        )
+        .remove(0);
+        assert!(matches!(row[..], [RType::Real(_), RType::Num(i64::MIN)]));
+        assert_eq!(row[0].to_string(), "9.22337203685478e+18");
+    }
+
+    #[test]
+    fn it_should_read_real_literals() {
+        let row = query("unsupported.db", "SELECT 1e-5, 2.5e+3, .5").remove(0);
+        assert_eq!(
+            row.iter()
+                .map(RType::to_string)
+                .collect::<Vec<_>>()
+                .join("|"),
+            "1.0e-05|2500.0|0.5"
+        );
+    }
+
+    #[test]
+    fn it_should_compare_with_the_collation_of_the_columns() {
+        let count = |sql| query("collations.db", sql)[0][0].clone();
+        // WITHOUT ROWID primary key and index seeks only find BINARY equal values
+        assert_eq!(
+            count("SELECT count(*) FROM keyed WHERE x = 'foo3'"),
+            RType::Num(1)
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM people WHERE name = 'alice'"),
+            RType::Num(3)
+        );
+        // The collation of the left column wins
+        assert_eq!(
+            count("SELECT count(*) FROM pairs WHERE a = b"),
+            RType::Num(2)
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM pairs WHERE b = a"),
+            RType::Num(1)
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM people q JOIN pairs p ON q.name = p.b"),
+            RType::Num(1)
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM people q JOIN pairs p ON p.b = q.name"),
+            RType::Num(0)
+        );
+    }
+
+    #[test]
+    fn it_should_compare_with_the_collate_operator() {
+        let count = |database, sql| query(database, sql)[0][0].clone();
+        assert_eq!(
+            count("indexes.db", "SELECT count(*) FROM t WHERE name = 'ALPHA'"),
+            RType::Num(0)
+        );
+        assert_eq!(
+            count(
+                "indexes.db",
+                "SELECT count(*) FROM t WHERE name COLLATE NOCASE = 'ALPHA'"
+            ),
+            RType::Num(600)
+        );
+        assert_eq!(
+            count(
+                "indexes.db",
+                "SELECT count(*) FROM t WHERE name COLLATE NOCASE IN ('ALPHA', 'gamma')"
+            ),
+            RType::Num(600)
+        );
+        // COLLATE wins over the collation of the columns, even on the right operand
+        assert_eq!(
+            count(
+                "collations.db",
+                "SELECT count(*) FROM pairs WHERE a = b COLLATE BINARY"
+            ),
+            RType::Num(1)
+        );
+        assert_eq!(
+            query(
+                "indexes.db",
+                "SELECT count(*) FROM t GROUP BY name COLLATE NOCASE"
+            ),
+            vec![vec![RType::Num(600)], vec![RType::Num(600)]]
+        );
+        let error = open("indexes.db")
+            .execute("SELECT 'a' COLLATE unknown")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "no such collation sequence: unknown");
+    }
+
+    #[test]
+    fn it_should_resolve_aliases_in_having() {
+        assert_eq!(
+            query(
+                "indexes.db",
+                "SELECT name AS k, count(*) AS c FROM t GROUP BY k HAVING c > 1 AND k < 'b' \
+                 ORDER BY k"
+            ),
+            vec![
+                vec![RType::Str("Alpha".to_string()), RType::Num(300)],
+                vec![RType::Str("BETA".to_string()), RType::Num(300)],
+                vec![RType::Str("alpha".to_string()), RType::Num(300)],
+            ]
+        );
+        // Columns of the table hide the aliases
+        assert_eq!(
+            query(
+                "indexes.db",
+                "SELECT n AS name, count(*) FROM t GROUP BY n HAVING name = 3"
+            ),
+            Vec::<Vec<RType>>::new()
+        );
+    }
+
+    #[test]
+    fn it_should_not_seek_indexes_with_another_collation_or_order() {
+        let count = |sql| query("indexes.db", sql)[0][0].clone();
+        assert_eq!(
+            count("SELECT count(*) FROM t WHERE name = 'alpha'"),
+            RType::Num(300)
+        );
+        assert_eq!(
+            count("SELECT count(*) FROM t WHERE name IN ('alpha', 'beta')"),
+            RType::Num(600)
+        );
+        assert_eq!(count("SELECT count(*) FROM t WHERE n = 7"), RType::Num(60));
+        assert_eq!(
+            count("SELECT count(*) FROM t WHERE n IN (3, 7)"),
+            RType::Num(120)
+        );
+    }
+
+    #[test]
+    fn it_should_plan_the_scan_of_the_first_table_of_a_join() {
+        let mut executor = open("joins.db");
+        let mut plan = |sql: &str| {
+            let Some(Ok(Statement::Select(select))) = Parser::new(sql).next() else {
+                panic!("Expected a SELECT statement");
+            };
+            let from_clause = select.from_clause.as_ref().unwrap();
+            let sources = executor.get_join_sources(from_clause).unwrap().unwrap();
+            let select = ColumnResolver::new(&sources)
+                .unwrap()
+                .resolve_query(&select)
+                .unwrap();
+            let outer_where =
+                join::get_outer_where(select.where_clause.as_ref().unwrap(), &sources[0].qualifier);
+            match executor.plan_scan(Some(&outer_where), &sources[0].table) {
+                ScanPlan::RowidRange { start, end } => format!("rowids {:?}..{:?}", start, end),
+                ScanPlan::IndexLookup { column, .. } => format!("index on {}", column),
+                ScanPlan::FullScan => "full scan".to_string(),
+                _ => "other".to_string(),
+            }
+        };
+        assert_eq!(
+            plan("SELECT * FROM authors a JOIN books b ON b.author_id = a.id WHERE a.id = 7"),
+            "rowids Some(7)..Some(7)"
+        );
+        assert_eq!(
+            plan("SELECT * FROM authors a, books b WHERE country = 'FR' AND b.author_id = a.id"),
+            "index on country"
+        );
+        // A condition on another table does not choose the rows of the first one
+        assert_eq!(
+            plan("SELECT * FROM authors a JOIN books b ON b.author_id = a.id WHERE b.id = 7"),
+            "full scan"
+        );
+    }
+
+    #[test]
+    fn it_should_join_the_rows_read_with_the_plan_of_the_first_table() {
+        let text = |value: &str| RType::Str(value.to_string());
+        assert_eq!(
+            query(
+                "joins.db",
+                "SELECT a.name, b.title FROM authors a JOIN books b ON b.author_id = a.id \
+                 WHERE a.id = 7"
+            ),
+            vec![
+                vec![text("author7"), text("book13")],
+                vec![text("author7"), text("book14")],
+            ]
+        );
+        assert_eq!(
+            query(
+                "joins.db",
+                "SELECT a.name, b.title FROM authors a, books b \
+                 WHERE a.country = 'FR' AND b.author_id = a.id LIMIT 3 OFFSET 1"
+            ),
+            vec![
+                vec![text("author3"), text("book6")],
+                vec![text("author6"), text("book11")],
+                vec![text("author6"), text("book12")],
+            ]
+        );
+        assert_eq!(
+            query(
+                "joins.db",
+                "SELECT a.name, b.title FROM authors a \
+                 LEFT JOIN books b ON b.author_id = a.id AND b.id > 13 WHERE a.id = 6"
+            ),
+            vec![vec![text("author6"), RType::Null]]
+        );
+    }
 }
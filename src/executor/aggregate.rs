@@ -0,0 +1,536 @@
+//! Grouping of the rows of an aggregate query.
+//!
+//! The rows are grouped by the values of the GROUP BY expressions with a hash table.
+//! When the scan returns the rows ordered by the group keys, the rows of a group
+//! are consecutive: a new group starts when the keys change and nothing is hashed.
+//!
+//! Each group keeps its first row, bare columns of the query are read from it,
+//! and one accumulator per aggregate call of the query. When the query has a single
+//! min() or max() call, the group keeps the row of the min or max instead.
+//!
+//! Aggregates ignore NULL values: count(x) counts the values that are not NULL,
+//! sum, avg, min, max and group_concat are NULL when there is no value and total is 0.0.
+//!
+//! See [Aggregate queries](https://www.sqlite.org/lang_select.html#resultset)
+use crate::db::fileformat::{header::TextEncoding, record::Record};
+use crate::db::table::Table;
+use crate::executor::collation::Collation;
+use crate::executor::db_response::RType;
+use crate::executor::eval::{apply_comparison_affinity, evaluate, to_real, to_text};
+use crate::parser::create_table::Affinity;
+use crate::parser::expr::Expr;
+use crate::parser::function::{FuncArgs, FuncCall};
+use anyhow::{Result, anyhow};
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+pub struct Grouping<'q> {
+    /// GROUP BY expressions with the collation comparing their values
+    keys: Vec<(Expr, Collation)>,
+    calls: &'q [&'q FuncCall],
+    // Initial state of the accumulators of a group
+    accumulators: Vec<Accumulator>,
+    // Position of the call when the query has a single min or max call
+    extreme_call: Option<usize>,
+    is_ordered: bool,
+    groups: Vec<Group>,
+    // Position of each group in `groups` by its keys
+    positions: HashMap<Vec<RType>, usize>,
+}
+
+impl<'q> Grouping<'q> {
+    /// `is_ordered` is true when rows with the same keys are consecutive
+    pub fn new(
+        keys: Vec<(Expr, Collation)>,
+        calls: &'q [&'q FuncCall],
+        is_ordered: bool,
+        table: &Table,
+    ) -> Result<Self> {
+        let accumulators = calls
+            .iter()
+            .map(|call| Accumulator::new(call, table))
+            .collect::<Result<Vec<_>>>()?;
+        let extreme_calls = accumulators
+            .iter()
+            .positions(|accumulator| matches!(accumulator.state, State::Extreme { .. }))
+            .collect::<Vec<_>>();
+        Ok(Self {
+            keys,
+            calls,
+            accumulators,
+            extreme_call: match extreme_calls.as_slice() {
+                [position] => Some(*position),
+                _ => None,
+            },
+            is_ordered,
+            groups: vec![],
+            positions: HashMap::new(),
+        })
+    }
+
+    pub fn add(&mut self, record: &Record) -> Result<()> {
+        let key = self
+            .keys
+            .iter()
+            .map(|(expr, collation)| Ok(collation.normalize(evaluate(expr, record)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let position = if self.is_ordered {
+            match self.groups.last() {
+                Some(group) if group.key == key => self.groups.len() - 1,
+                _ => self.push_group(key, record),
+            }
+        } else {
+            match self.positions.get(&key) {
+                Some(position) => *position,
+                None => {
+                    let position = self.push_group(key.clone(), record);
+                    self.positions.insert(key, position);
+                    position
+                }
+            }
+        };
+        let group = &mut self.groups[position];
+        for (i, (accumulator, call)) in group.accumulators.iter_mut().zip(self.calls).enumerate() {
+            let args = match &call.args {
+                FuncArgs::Star => vec![],
+                FuncArgs::List(args) => args
+                    .iter()
+                    .map(|arg| evaluate(arg, record))
+                    .collect::<Result<Vec<_>>>()?,
+            };
+            let is_new_extreme = accumulator.step(args, record.get_encoding())?;
+            // With a single min or max, bare columns are read from the row of the min or max
+            if is_new_extreme && self.extreme_call == Some(i) {
+                group.row = record.get_values();
+            }
+        }
+        Ok(())
+    }
+
+    fn push_group(&mut self, key: Vec<RType>, record: &Record) -> usize {
+        self.groups.push(Group {
+            key,
+            row: record.get_values(),
+            accumulators: self.accumulators.clone(),
+        });
+        self.groups.len() - 1
+    }
+
+    /// Returns the groups in the order of their first row.
+    /// Without GROUP BY, all the rows are one group, even when there is no row.
+    pub fn finish(mut self, columns_len: usize) -> Vec<Group> {
+        if self.keys.is_empty() && self.groups.is_empty() {
+            self.groups.push(Group {
+                key: vec![],
+                row: vec![RType::Null; columns_len],
+                accumulators: self.accumulators,
+            });
+        }
+        self.groups
+    }
+}
+
+pub struct Group {
+    key: Vec<RType>,
+    /// Values of the columns of the row bare columns are read from
+    pub row: Vec<RType>,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Group {
+    /// Returns the row of the group and the value of each aggregate call
+    pub fn finish<'q>(
+        self,
+        calls: &'q [&'q FuncCall],
+    ) -> Result<(Vec<RType>, AggregateValues<'q>)> {
+        let values = self
+            .accumulators
+            .iter()
+            .map(Accumulator::finalize)
+            .collect::<Result<_>>()?;
+        Ok((self.row, AggregateValues { calls, values }))
+    }
+}
+
+/// The value of each aggregate call for a group
+pub struct AggregateValues<'q> {
+    calls: &'q [&'q FuncCall],
+    values: Vec<RType>,
+}
+
+impl AggregateValues<'_> {
+    /// Evaluate an expression for the group, aggregate calls take the group values
+    pub fn evaluate(&self, expr: &Expr, record: &Record) -> Result<RType> {
+        evaluate(&self.replace_aggregates(expr), record)
+    }
+
+    fn replace_aggregates(&self, expr: &Expr) -> Expr {
+        expr.transform(&|expr| match expr {
+            Expr::Function(func) if func.is_aggregate() => self
+                .calls
+                .iter()
+                .position(|call| *call == func)
+                .map(|position| Expr::Literal(self.values[position].clone())),
+            _ => None,
+        })
+    }
+}
+
+// State of an aggregate call for one group: `step` adds the arguments of a row,
+// `finalize` returns the value of the call.
+#[derive(Clone)]
+struct Accumulator {
+    state: State,
+    // Values already added by a DISTINCT call
+    seen: Option<HashSet<RType>>,
+}
+
+#[derive(Clone)]
+enum State {
+    Count(i64),
+    Sum {
+        function: SumFunction,
+        sum: Sum,
+        count: i64,
+    },
+    /// min keeps the values ordered `Less`, max the values ordered `Greater`
+    Extreme {
+        value: RType,
+        ordering: Ordering,
+        collation: Collation,
+    },
+    GroupConcat(Option<String>),
+}
+
+#[derive(Clone, Copy)]
+enum SumFunction {
+    Sum,
+    Total,
+    Avg,
+}
+
+impl Accumulator {
+    fn new(call: &FuncCall, table: &Table) -> Result<Self> {
+        let args = match &call.args {
+            FuncArgs::Star => None,
+            FuncArgs::List(args) => Some(args.as_slice()),
+        };
+        let sum = |function| State::Sum {
+            function,
+            sum: Sum::default(),
+            count: 0,
+        };
+        let state = match (call.function_name.as_str(), args) {
+            ("count", None | Some([] | [_])) => State::Count(0),
+            ("sum", Some([_])) => sum(SumFunction::Sum),
+            ("total", Some([_])) => sum(SumFunction::Total),
+            ("avg", Some([_])) => sum(SumFunction::Avg),
+            (name @ ("min" | "max"), Some([arg])) => State::Extreme {
+                value: RType::Null,
+                ordering: if name == "min" {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                },
+                collation: Collation::from_expr(arg, table)?,
+            },
+            ("group_concat", Some([_] | [_, _])) => State::GroupConcat(None),
+            _ => {
+                return Err(anyhow!(
+                    "wrong number of arguments to function {}()",
+                    call.function_name
+                ));
+            }
+        };
+        let seen = if call.distinct {
+            if !matches!(args, Some([_])) {
+                return Err(anyhow!(
+                    "DISTINCT aggregates must have exactly one argument"
+                ));
+            }
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        Ok(Self { state, seen })
+    }
+
+    // Returns true when the value is the new min or max.
+    // `encoding` is the database text encoding, that orders text values.
+    fn step(&mut self, args: Vec<RType>, encoding: TextEncoding) -> Result<bool> {
+        let mut args = args.into_iter();
+        let Some(value) = args.next() else {
+            // count(*) counts all the rows
+            if let State::Count(count) = &mut self.state {
+                *count += 1;
+            }
+            return Ok(false);
+        };
+        // Aggregates ignore NULL values
+        if matches!(value, RType::Null) {
+            return Ok(false);
+        }
+        if let Some(seen) = &mut self.seen
+            && !seen.insert(value.clone())
+        {
+            return Ok(false);
+        }
+        match &mut self.state {
+            State::Count(count) => *count += 1,
+            State::Sum { sum, count, .. } => {
+                sum.add(value);
+                *count += 1;
+            }
+            State::Extreme {
+                value: extreme,
+                ordering,
+                collation,
+            } => {
+                if matches!(extreme, RType::Null)
+                    || collation.compare(&value, extreme, encoding) == *ordering
+                {
+                    *extreme = value;
+                    return Ok(true);
+                }
+            }
+            State::GroupConcat(text) => {
+                let value = to_text(&value);
+                match text {
+                    None => *text = Some(value),
+                    Some(text) => {
+                        // The separator of each row comes before its value
+                        let separator = match args.next() {
+                            None => ",".to_string(),
+                            Some(RType::Null) => String::new(),
+                            Some(separator) => to_text(&separator),
+                        };
+                        text.push_str(&separator);
+                        text.push_str(&value);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn finalize(&self) -> Result<RType> {
+        let value = match &self.state {
+            State::Count(count) => RType::Num(*count),
+            // sum is NULL without values, total is 0.0
+            State::Sum {
+                function: SumFunction::Sum,
+                count: 0,
+                ..
+            } => RType::Null,
+            State::Sum {
+                function: SumFunction::Sum,
+                sum,
+                ..
+            } => sum.value()?,
+            State::Sum {
+                function: SumFunction::Total,
+                sum,
+                ..
+            } => RType::Real(sum.total()),
+            State::Sum {
+                function: SumFunction::Avg,
+                count: 0,
+                ..
+            } => RType::Null,
+            State::Sum {
+                function: SumFunction::Avg,
+                sum,
+                count,
+            } => RType::Real(sum.total() / *count as f64),
+            State::Extreme { value, .. } => value.clone(),
+            State::GroupConcat(text) => text.clone().map_or(RType::Null, RType::Str),
+        };
+        Ok(value)
+    }
+}
+
+// Sum of integers, it becomes a sum of reals once a value is not an integer.
+// Text that looks like a number is added as a number, other text as 0.0.
+#[derive(Clone, Default)]
+struct Sum {
+    int: i64,
+    real: f64,
+    is_real: bool,
+    overflow: bool,
+}
+
+impl Sum {
+    fn add(&mut self, value: RType) {
+        match apply_comparison_affinity(value, Some(Affinity::Numeric)) {
+            RType::Num(value) if !self.is_real => match self.int.checked_add(value) {
+                Some(int) => self.int = int,
+                None => {
+                    self.overflow = true;
+                    self.is_real = true;
+                    self.real = self.int as f64 + value as f64;
+                }
+            },
+            value => {
+                if !self.is_real {
+                    self.is_real = true;
+                    self.real = self.int as f64;
+                }
+                self.real += to_real(&value);
+            }
+        }
+    }
+
+    // Value of sum(): an integer unless a value is not an integer
+    fn value(&self) -> Result<RType> {
+        if self.overflow {
+            return Err(anyhow!("integer overflow"));
+        }
+        if self.is_real {
+            Ok(RType::Real(self.real))
+        } else {
+            Ok(RType::Num(self.int))
+        }
+    }
+
+    // Value of total(), always a real
+    fn total(&self) -> f64 {
+        if self.is_real {
+            self.real
+        } else {
+            self.int as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_groups(is_ordered: bool) -> Vec<(Vec<RType>, Vec<RType>)> {
+        let table = Table::from_view_columns("apples", vec!["color".into(), "size".into()]);
+        let rows = [
+            ("red", RType::Num(1)),
+            ("red", RType::Null),
+            ("Green", RType::Num(2)),
+            ("green", RType::Num(3)),
+        ];
+        let count_star = FuncCall::new("count".into(), FuncArgs::Star);
+        let count_size = FuncCall::new(
+            "count".into(),
+            FuncArgs::List(vec![Expr::Column("size".into())]),
+        );
+        let calls = [&count_star, &count_size];
+        let keys = vec![(Expr::Column("color".into()), Collation::NoCase)];
+        let mut grouping = Grouping::new(keys, &calls, is_ordered, &table).unwrap();
+        for (color, size) in rows {
+            let record = Record::from_values(
+                vec![RType::Str(color.into()), size],
+                &table,
+                TextEncoding::Utf8,
+            );
+            grouping.add(&record).unwrap();
+        }
+        grouping
+            .finish(2)
+            .into_iter()
+            .map(|group| {
+                let (row, values) = group.finish(&calls).unwrap();
+                (row, values.values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_should_group_rows() {
+        for is_ordered in [false, true] {
+            let groups = count_groups(is_ordered);
+            assert_eq!(groups.len(), 2);
+            assert_eq!(groups[0].0[0], RType::Str("red".into()));
+            assert_eq!(groups[0].1, vec![RType::Num(2), RType::Num(1)]);
+            assert_eq!(groups[1].0[0], RType::Str("Green".into()));
+            assert_eq!(groups[1].1, vec![RType::Num(2), RType::Num(2)]);
+        }
+    }
+
+    #[test]
+    fn it_should_return_one_group_without_group_by() {
+        let count_star = FuncCall::new("count".into(), FuncArgs::Star);
+        let calls = [&count_star];
+        let table = Table::from_view_columns("apples", vec!["color".into()]);
+        let grouping = Grouping::new(vec![], &calls, true, &table).unwrap();
+        let groups = grouping.finish(1);
+        assert_eq!(groups.len(), 1);
+        let (row, values) = groups.into_iter().next().unwrap().finish(&calls).unwrap();
+        assert_eq!(row, vec![RType::Null]);
+        assert_eq!(values.values, vec![RType::Num(0)]);
+    }
+
+    // Value of the aggregate function over the values of a column
+    fn aggregate(function_name: &str, distinct: bool, values: &[RType]) -> Result<RType> {
+        let table = Table::from_view_columns("apples", vec!["size".into()]);
+        let mut call = FuncCall::new(
+            function_name.into(),
+            FuncArgs::List(vec![Expr::Column("size".into())]),
+        );
+        call.distinct = distinct;
+        let mut accumulator = Accumulator::new(&call, &table)?;
+        for value in values {
+            accumulator.step(vec![value.clone()], TextEncoding::Utf8)?;
+        }
+        accumulator.finalize()
+    }
+
+    #[test]
+    fn it_should_compute_aggregates() {
+        let values = [
+            RType::Num(1),
+            RType::Null,
+            RType::Str("2".into()),
+            RType::Num(1),
+        ];
+        let cases = [
+            ("count", false, RType::Num(3)),
+            ("count", true, RType::Num(2)),
+            ("sum", false, RType::Num(4)),
+            ("sum", true, RType::Num(3)),
+            ("total", false, RType::Real(4.0)),
+            ("avg", false, RType::Real(4.0 / 3.0)),
+            ("min", false, RType::Num(1)),
+            ("max", false, RType::Str("2".into())),
+            ("group_concat", false, RType::Str("1,2,1".into())),
+            ("group_concat", true, RType::Str("1,2".into())),
+        ];
+        for (function_name, distinct, expected) in cases {
+            assert_eq!(
+                aggregate(function_name, distinct, &values).unwrap(),
+                expected,
+                "{}",
+                function_name
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_apply_null_rules_to_aggregates() {
+        let nulls = [RType::Null];
+        assert_eq!(aggregate("count", false, &nulls).unwrap(), RType::Num(0));
+        assert_eq!(aggregate("sum", false, &nulls).unwrap(), RType::Null);
+        assert_eq!(aggregate("total", false, &nulls).unwrap(), RType::Real(0.0));
+        assert_eq!(aggregate("avg", false, &nulls).unwrap(), RType::Null);
+        assert_eq!(aggregate("max", false, &nulls).unwrap(), RType::Null);
+        assert_eq!(
+            aggregate("group_concat", false, &nulls).unwrap(),
+            RType::Null
+        );
+        // A text that is not a number makes the sum a real
+        let values = [RType::Num(1), RType::Str("abc".into())];
+        assert_eq!(aggregate("sum", false, &values).unwrap(), RType::Real(1.0));
+        let values = [RType::Num(i64::MAX), RType::Num(1)];
+        assert!(aggregate("sum", false, &values).is_err());
+        assert_eq!(
+            aggregate("total", false, &values).unwrap(),
+            RType::Real(i64::MAX as f64 + 1.0)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Collating sequences used to compare text values.
+//!
+//! See [Collating Sequences](https://www.sqlite.org/datatype3.html#collation)
+use crate::db::cursor::compare_values;
+use crate::db::fileformat::header::TextEncoding;
+use crate::db::table::Table;
+use crate::executor::db_response::RType;
+use crate::parser::expr::Expr;
+use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Collation {
+    /// Compare the bytes of the text, in the database encoding
+    #[default]
+    Binary,
+    /// Compare the UTF-8 bytes of the text, ASCII upper case letters are folded to lower case
+    NoCase,
+    /// Compare the UTF-8 bytes of the text, trailing spaces are ignored
+    RTrim,
+}
+
+impl Collation {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "binary" => Ok(Collation::Binary),
+            "nocase" => Ok(Collation::NoCase),
+            "rtrim" => Ok(Collation::RTrim),
+            _ => Err(anyhow!("no such collation sequence: {}", name)),
+        }
+    }
+
+    /// The collation of a COLLATE expression, the declared collation of a column,
+    /// BINARY for other expressions
+    pub fn from_expr(expr: &Expr, table: &Table) -> Result<Self> {
+        let collation = match expr {
+            Expr::Collate { collation, .. } => Some(collation.as_str()),
+            Expr::Column(col_name) => table
+                .get_column_index(col_name)
+                .and_then(|index| table.get_column_collation(index)),
+            _ => None,
+        };
+        match collation {
+            Some(collation) => Collation::from_name(collation),
+            None => Ok(Collation::Binary),
+        }
+    }
+
+    /// Collation of a comparison: the COLLATE of the left operand, else of the right operand,
+    /// else the declared collation of the left operand when it is a column, else of the
+    /// right operand when it is a column, else BINARY.
+    /// See 7.1 Assigning Collating Sequences from SQL in [Datatypes](https://www.sqlite.org/datatype3.html)
+    pub fn for_comparison(left: &Expr, right: &Expr, table: &Table) -> Result<Self> {
+        match (left, right) {
+            (Expr::Collate { .. }, _) => Collation::from_expr(left, table),
+            (_, Expr::Collate { .. }) => Collation::from_expr(right, table),
+            (Expr::Column(_), _) => Collation::from_expr(left, table),
+            (_, Expr::Column(_)) => Collation::from_expr(right, table),
+            _ => Ok(Collation::Binary),
+        }
+    }
+
+    /// Returns a value equal to all the values the collation compares as equal to `value`
+    pub fn normalize(&self, value: RType) -> RType {
+        match (self, value) {
+            (Collation::NoCase, RType::Str(text)) => RType::Str(text.to_ascii_lowercase()),
+            (Collation::RTrim, RType::Str(text)) => {
+                RType::Str(text.trim_end_matches(' ').to_string())
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// Compare two values, only text values are affected by the collation.
+    /// `encoding` is the database text encoding.
+    pub fn compare(&self, left: &RType, right: &RType, encoding: TextEncoding) -> Ordering {
+        let (RType::Str(left_text), RType::Str(right_text)) = (left, right) else {
+            return left.cmp(right);
+        };
+        match self {
+            Collation::Binary => compare_values(left, right, encoding),
+            Collation::NoCase => left_text
+                .bytes()
+                .map(|byte| byte.to_ascii_lowercase())
+                .cmp(right_text.bytes().map(|byte| byte.to_ascii_lowercase())),
+            Collation::RTrim => left_text
+                .trim_end_matches(' ')
+                .cmp(right_text.trim_end_matches(' ')),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_compare_text_with_collation() {
+        let text = |text: &str| RType::Str(text.to_string());
+        assert_eq!(
+            Collation::Binary.compare(&text("B"), &text("a"), TextEncoding::Utf8),
+            Ordering::Less
+        );
+        assert_eq!(
+            Collation::NoCase.compare(&text("B"), &text("a"), TextEncoding::Utf8),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Collation::NoCase.compare(&text("ABC"), &text("abc"), TextEncoding::Utf8),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Collation::RTrim.compare(&text("abc  "), &text("abc"), TextEncoding::Utf8),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Collation::NoCase.compare(&RType::Num(1), &text("a"), TextEncoding::Utf8),
+            Ordering::Less
+        );
+        // BINARY compares the bytes of the database encoding, U+0100 is 0x00 0x01 in UTF-16LE
+        assert_eq!(
+            Collation::Binary.compare(&text("\u{100}"), &text("b"), TextEncoding::Utf16le),
+            Ordering::Less
+        );
+        assert_eq!(
+            Collation::NoCase.compare(&text("\u{100}"), &text("b"), TextEncoding::Utf16le),
+            Ordering::Greater
+        );
+    }
+}
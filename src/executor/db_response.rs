@@ -5,34 +5,129 @@
 //!
 //! # Example
 //! ```no_run
-//! use codecrafters_sqlite::db::db_response::{RType, Response};
-//! let db = codecrafters_sqlite::db::DB::new("test.db").unwrap();
-//! let response = db.execute("SELECT name, age, photo FROM users;").unwrap();
-//! if let Some(responses) = response {
-//!    for (_query, response) in responses {
-//!         for row in response {
-//!             for col in row {
-//!                 println!("{}", col);
-//!             }
+//! use codecrafters_sqlite::{db::DB, executor::Executor};
+//! let db = DB::new("test.db").unwrap();
+//! let mut executor = Executor::new(db);
+//! let responses = executor.execute("SELECT name, age, photo FROM users;").unwrap();
+//! for (_query, response) in responses {
+//!     for row in response {
+//!         for col in row {
+//!             println!("{}", col);
 //!         }
-//!    }
+//!     }
 //! }
 //! ```
 //!
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter, Result};
+use std::hash::{Hash, Hasher};
 
-#[derive(PartialEq, Clone, Debug, PartialOrd, Eq, Ord)]
+#[derive(Clone, Debug)]
 pub enum RType {
+    Null,
     Num(i64),
-    Blob(Vec<u8>),
+    Real(f64),
     Str(String),
-    Null,
+    Blob(Vec<u8>),
+}
+
+impl RType {
+    // Rank of the value type in the sqlite ordering: NULL < numbers < text < blob
+    fn type_rank(&self) -> u8 {
+        match self {
+            RType::Null => 0,
+            RType::Num(_) | RType::Real(_) => 1,
+            RType::Str(_) => 2,
+            RType::Blob(_) => 3,
+        }
+    }
+}
+
+/// Booleans are integers: true is 1 and false is 0.
+impl From<bool> for RType {
+    fn from(value: bool) -> Self {
+        RType::Num(value as i64)
+    }
+}
+
+/// Integers and reals are compared by their numeric value, so `1 = 1.0`.
+impl PartialEq for RType {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RType {}
+
+impl PartialOrd for RType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Values are ordered like sqlite sorts them: NULL < numbers < text < blob.
+/// Text and blobs are compared byte by byte.
+impl Ord for RType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (RType::Num(left), RType::Num(right)) => left.cmp(right),
+            (RType::Real(left), RType::Real(right)) => compare_reals(*left, *right),
+            (RType::Num(left), RType::Real(right)) => compare_int_real(*left, *right),
+            (RType::Real(left), RType::Num(right)) => compare_int_real(*right, *left).reverse(),
+            (RType::Str(left), RType::Str(right)) => left.cmp(right),
+            (RType::Blob(left), RType::Blob(right)) => left.cmp(right),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+/// Hashing agrees with equality: a real without fractional part hashes like the integer.
+impl Hash for RType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_rank().hash(state);
+        match self {
+            RType::Null => {}
+            RType::Num(value) => value.hash(state),
+            RType::Real(value)
+                if value.fract() == 0.0
+                    && *value >= i64::MIN as f64
+                    && *value < i64::MAX as f64 =>
+            {
+                (*value as i64).hash(state)
+            }
+            RType::Real(value) => value.to_bits().hash(state),
+            RType::Str(value) => value.hash(state),
+            RType::Blob(value) => value.hash(state),
+        }
+    }
+}
+
+// Sqlite never stores NaN, so reals are always comparable
+fn compare_reals(left: f64, right: f64) -> Ordering {
+    left.partial_cmp(&right).unwrap_or(Ordering::Equal)
+}
+
+// Compare an integer with a real without losing the integer precision
+// by converting it to a float.
+fn compare_int_real(int: i64, real: f64) -> Ordering {
+    if real < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    if real >= i64::MAX as f64 {
+        return Ordering::Less;
+    }
+    let truncated = real.trunc();
+    match int.cmp(&(truncated as i64)) {
+        Ordering::Equal => compare_reals(0.0, real - truncated),
+        ordering => ordering,
+    }
 }
 
 impl Display for RType {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match self {
             RType::Num(value) => write!(f, "{}", value),
+            RType::Real(value) => write!(f, "{}", format_real(*value)),
             RType::Blob(value) => write!(f, "{:?}", value),
             RType::Str(value) => write!(f, "{}", value),
             RType::Null => write!(f, "Null"),
@@ -40,4 +135,85 @@ impl Display for RType {
     }
 }
 
+/// Format a real like sqlite3 does (printf "%!.15g"): 15 significant digits,
+/// scientific notation for small and big exponents, and always a decimal point.
+fn format_real(value: f64) -> String {
+    if value.is_infinite() {
+        return if value > 0.0 { "Inf" } else { "-Inf" }.to_string();
+    }
+    let scientific = format!("{:.14e}", value);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("Scientific notation always has an exponent");
+    let exponent: i32 = exponent.parse().expect("Exponent is an integer");
+
+    if !(-4..15).contains(&exponent) {
+        let mantissa = trim_fraction(mantissa);
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    } else {
+        let decimals = (14 - exponent) as usize;
+        trim_fraction(&format!("{:.*}", decimals, value))
+    }
+}
+
+// Remove the trailing zeros of the fractional part, keeping at least one digit
+fn trim_fraction(number: &str) -> String {
+    let trimmed = number.trim_end_matches('0');
+    if trimmed.ends_with('.') {
+        format!("{}0", trimmed)
+    } else if trimmed.contains('.') {
+        trimmed.to_string()
+    } else {
+        format!("{}.0", trimmed)
+    }
+}
+
 pub type Response = Vec<Vec<RType>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_display_reals_like_sqlite() {
+        let reals = [1.0, 3.25, 1e20, 0.1, 1e-5, 1.5e15, -2.5, 1.0 / 3.0, 100.0];
+        let expected = [
+            "1.0",
+            "3.25",
+            "1.0e+20",
+            "0.1",
+            "1.0e-05",
+            "1.5e+15",
+            "-2.5",
+            "0.333333333333333",
+            "100.0",
+        ];
+        for (real, expected) in reals.into_iter().zip(expected) {
+            assert_eq!(RType::Real(real).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn it_should_compare_integers_and_reals() {
+        assert_eq!(RType::Num(1), RType::Real(1.0));
+        assert!(RType::Num(1) < RType::Real(1.5));
+        assert!(RType::Real(-0.5) < RType::Num(0));
+        assert!(RType::Num(i64::MAX) < RType::Real(1e19));
+    }
+
+    #[test]
+    fn it_should_hash_equal_numbers_alike() {
+        use std::collections::HashSet;
+        let values = HashSet::from([RType::Num(1), RType::Real(1.0), RType::Real(-0.0)]);
+        assert_eq!(values.len(), 2);
+        assert!(values.contains(&RType::Num(0)));
+    }
+
+    #[test]
+    fn it_should_order_types_like_sqlite() {
+        assert!(RType::Null < RType::Num(-5));
+        assert!(RType::Real(1e300) < RType::Str("".to_string()));
+        assert!(RType::Str("z".to_string()) < RType::Blob(vec![]));
+    }
+}
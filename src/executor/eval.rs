@@ -0,0 +1,882 @@
+//! Evaluate expressions against the values of a row.
+//!
+//! Values follow the sqlite rules:
+//! * an operation with a NULL operand is NULL
+//! * arithmetic operators convert text and blobs to numbers
+//! * integer operations that overflow give reals
+//! * a division or a modulo by zero is NULL
+//! * `AND`, `OR` and `NOT` use three-valued logic: NULL is unknown,
+//!   `NULL AND 0` is 0 and `NULL OR 1` is 1
+//! * comparisons order values as NULL < numbers < text < blob, after converting
+//!   the operands with the affinity of the compared columns
+//! * `IS` and `IS NOT` compare NULL like any other value
+//! * `LIKE`, `ILIKE` and `GLOB` match the text of their operands, see [pattern](crate::executor::pattern)
+//!
+//! See [Expressions](https://www.sqlite.org/lang_expr.html)
+use crate::db::cursor::compare_values;
+use crate::db::fileformat::{header::TextEncoding, record::Record};
+use crate::db::table::Table;
+use crate::executor::collation::Collation;
+use crate::executor::db_response::RType;
+use crate::executor::pattern::{glob, like};
+use crate::parser::create_table::Affinity;
+use crate::parser::expr::{BinaryOperator, Expr, LikeOperator, UnaryOperator};
+use crate::parser::function::{FuncArgs, FuncCall};
+use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+
+pub fn evaluate(expr: &Expr, record: &Record) -> Result<RType> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Column(name) => record
+            .get_value(name)
+            .ok_or_else(|| anyhow!("no such column: {}", name)),
+        Expr::Collate { expr, collation } => {
+            Collation::from_name(collation)?;
+            evaluate(expr, record)
+        }
+        Expr::Unary { operator, expr } => {
+            let value = evaluate(expr, record)?;
+            Ok(evaluate_unary(*operator, value))
+        }
+        Expr::Binary {
+            left,
+            operator: operator @ (BinaryOperator::And | BinaryOperator::Or),
+            right,
+        } => evaluate_logical(left, *operator, right, record),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } if operator.is_comparison() => {
+            let left_value = evaluate(left, record)?;
+            let right_value = evaluate(right, record)?;
+            compare_operands(left, &left_value, *operator, right, right_value, record)
+        }
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = evaluate(left, record)?;
+            let right = evaluate(right, record)?;
+            Ok(evaluate_binary(left, *operator, right))
+        }
+        Expr::Function(func) => evaluate_function(func, record),
+        Expr::Like {
+            expr,
+            operator,
+            pattern,
+            escape,
+            negated,
+        } => {
+            let escape = escape.as_deref();
+            evaluate_like(expr, *operator, pattern, escape, *negated, record)
+        }
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => evaluate_in_list(expr, list, *negated, record),
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => {
+            let value = evaluate(expr, record)?;
+            let compare_bound = |operator, bound: &Expr| -> Result<Option<bool>> {
+                let bound_value = evaluate(bound, record)?;
+                let result = compare_operands(expr, &value, operator, bound, bound_value, record)?;
+                Ok(to_bool(&result))
+            };
+            let is_between = match (
+                compare_bound(BinaryOperator::GTE, low)?,
+                compare_bound(BinaryOperator::LTE, high)?,
+            ) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            };
+            Ok(from_bool(
+                is_between.map(|is_between| is_between != *negated),
+            ))
+        }
+    }
+}
+
+fn evaluate_like(
+    expr: &Expr,
+    operator: LikeOperator,
+    pattern: &Expr,
+    escape: Option<&Expr>,
+    negated: bool,
+    record: &Record,
+) -> Result<RType> {
+    let text = evaluate(expr, record)?;
+    let pattern = evaluate(pattern, record)?;
+    let escape = match escape {
+        Some(escape) => Some(evaluate(escape, record)?),
+        None => None,
+    };
+    if [Some(&text), Some(&pattern), escape.as_ref()]
+        .iter()
+        .any(|value| matches!(value, Some(RType::Null)))
+    {
+        return Ok(RType::Null);
+    }
+    let escape = match escape {
+        Some(escape) => {
+            let escape = to_text(&escape);
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(escape), None) => Some(escape),
+                _ => return Err(anyhow!("ESCAPE expression must be a single character")),
+            }
+        }
+        None => None,
+    };
+    let (text, pattern) = (to_text(&text), to_text(&pattern));
+    let matched = match operator {
+        LikeOperator::Like => like(&text, &pattern, escape, false),
+        LikeOperator::ILike => like(&text, &pattern, escape, true),
+        LikeOperator::Glob => glob(&text, &pattern),
+    };
+    Ok(RType::from(matched != negated))
+}
+
+// NULL when the value is not found and the list contains NULL,
+// the value could be any of the unknown values
+fn evaluate_in_list(expr: &Expr, list: &[Expr], negated: bool, record: &Record) -> Result<RType> {
+    if list.is_empty() {
+        return Ok(RType::from(negated));
+    }
+    let value = evaluate(expr, record)?;
+    let mut has_null = false;
+    for item in list {
+        let item_value = evaluate(item, record)?;
+        match to_bool(&compare_operands(
+            expr,
+            &value,
+            BinaryOperator::Eq,
+            item,
+            item_value,
+            record,
+        )?) {
+            Some(true) => return Ok(RType::from(!negated)),
+            Some(false) => {}
+            None => has_null = true,
+        }
+    }
+    if has_null {
+        Ok(RType::Null)
+    } else {
+        Ok(RType::from(negated))
+    }
+}
+
+/// Evaluate an expression that does not depend on a row, like the LIMIT of a query
+pub fn evaluate_constant(expr: &Expr) -> Result<RType> {
+    let table = Table::from_view_columns("", vec![]);
+    evaluate(
+        expr,
+        &Record::from_values(vec![], &table, TextEncoding::Utf8),
+    )
+}
+
+/// A condition is true when its value is a non zero number.
+/// NULL is not true.
+pub fn is_true(value: &RType) -> bool {
+    to_bool(value).unwrap_or(false)
+}
+
+fn from_bool(value: Option<bool>) -> RType {
+    value.map_or(RType::Null, RType::from)
+}
+
+/// Truth value of a condition, None when it is NULL
+fn to_bool(value: &RType) -> Option<bool> {
+    match to_numeric(value) {
+        RType::Num(value) => Some(value != 0),
+        RType::Real(value) => Some(value != 0.0),
+        _ => None,
+    }
+}
+
+fn evaluate_unary(operator: UnaryOperator, value: RType) -> RType {
+    match (operator, to_numeric(&value)) {
+        // Unary plus is a no-op, even for text
+        (UnaryOperator::Plus, _) => value,
+        (UnaryOperator::Minus, RType::Num(value)) => value
+            .checked_neg()
+            .map_or(RType::Real(-(value as f64)), RType::Num),
+        (UnaryOperator::Minus, RType::Real(value)) => RType::Real(-value),
+        (UnaryOperator::Minus, _) => RType::Null,
+        (UnaryOperator::Not, _) => from_bool(to_bool(&value).map(|value| !value)),
+    }
+}
+
+// The right operand is only evaluated when the left one does not decide the result
+fn evaluate_logical(
+    left: &Expr,
+    operator: BinaryOperator,
+    right: &Expr,
+    record: &Record,
+) -> Result<RType> {
+    // AND is decided by a false operand, OR by a true one
+    let decisive = operator == BinaryOperator::Or;
+    let left = to_bool(&evaluate(left, record)?);
+    if left == Some(decisive) {
+        return Ok(RType::from(decisive));
+    }
+    let right = to_bool(&evaluate(right, record)?);
+    if right == Some(decisive) {
+        return Ok(RType::from(decisive));
+    }
+    if left.is_none() || right.is_none() {
+        return Ok(RType::Null);
+    }
+    Ok(RType::from(!decisive))
+}
+
+fn evaluate_binary(left: RType, operator: BinaryOperator, right: RType) -> RType {
+    if matches!(left, RType::Null) || matches!(right, RType::Null) {
+        return RType::Null;
+    }
+    match operator {
+        BinaryOperator::Concat => RType::Str(to_text(&left) + &to_text(&right)),
+        BinaryOperator::Add => arithmetic(&left, &right, i64::checked_add, |l, r| l + r),
+        BinaryOperator::Sub => arithmetic(&left, &right, i64::checked_sub, |l, r| l - r),
+        BinaryOperator::Mul => arithmetic(&left, &right, i64::checked_mul, |l, r| l * r),
+        BinaryOperator::Div => divide(&left, &right),
+        BinaryOperator::Mod => modulo(&left, &right),
+        operator => unreachable!("{} is not an arithmetic operator", operator),
+    }
+}
+
+fn compare(
+    left: &RType,
+    operator: BinaryOperator,
+    right: &RType,
+    collation: Collation,
+    encoding: TextEncoding,
+) -> RType {
+    let ordering = collation.compare(left, right, encoding);
+    match operator {
+        BinaryOperator::Is => RType::from(ordering.is_eq()),
+        BinaryOperator::IsNot => RType::from(ordering.is_ne()),
+        _ if matches!(left, RType::Null) || matches!(right, RType::Null) => RType::Null,
+        BinaryOperator::LT => RType::from(ordering.is_lt()),
+        BinaryOperator::LTE => RType::from(ordering.is_le()),
+        BinaryOperator::GT => RType::from(ordering.is_gt()),
+        BinaryOperator::GTE => RType::from(ordering.is_ge()),
+        BinaryOperator::Eq => RType::from(ordering.is_eq()),
+        BinaryOperator::NotEq => RType::from(ordering.is_ne()),
+        operator => unreachable!("{} is not a comparison operator", operator),
+    }
+}
+
+// Compare the values of two expressions, after applying the affinity of the expressions,
+// with the collation of the comparison
+fn compare_operands(
+    left_expr: &Expr,
+    left: &RType,
+    operator: BinaryOperator,
+    right_expr: &Expr,
+    right: RType,
+    record: &Record,
+) -> Result<RType> {
+    let affinity = comparison_affinity(
+        expr_affinity(left_expr, record),
+        expr_affinity(right_expr, record),
+    );
+    let collation = Collation::for_comparison(left_expr, right_expr, record.get_table())?;
+    let left = apply_comparison_affinity(left.clone(), affinity);
+    let right = apply_comparison_affinity(right, affinity);
+    Ok(compare(
+        &left,
+        operator,
+        &right,
+        collation,
+        record.get_encoding(),
+    ))
+}
+
+// Columns have the affinity of their declared type, COLLATE keeps the affinity of its
+// operand, other expressions have none
+fn expr_affinity(expr: &Expr, record: &Record) -> Option<Affinity> {
+    match expr {
+        Expr::Column(name) => record.get_column_affinity(name),
+        Expr::Collate { expr, .. } => expr_affinity(expr, record),
+        _ => None,
+    }
+}
+
+/// Affinity applied to both operands of a comparison, None when the values are
+/// compared as they are.
+/// See 4.2. Type Conversions Prior To Comparison in [Datatypes](https://www.sqlite.org/datatype3.html)
+pub fn comparison_affinity(left: Option<Affinity>, right: Option<Affinity>) -> Option<Affinity> {
+    match (left, right) {
+        (Some(left), Some(right)) if left.is_numeric() || right.is_numeric() => {
+            Some(Affinity::Numeric)
+        }
+        (Some(_), Some(_)) => None,
+        (Some(affinity), None) | (None, Some(affinity)) => Some(affinity),
+        (None, None) => None,
+    }
+}
+
+/// Numeric affinity converts text that looks like a number, TEXT affinity converts
+/// numbers to text. BLOB affinity does not convert anything.
+pub fn apply_comparison_affinity(value: RType, affinity: Option<Affinity>) -> RType {
+    match (affinity, value) {
+        (Some(affinity), RType::Str(text)) if affinity.is_numeric() => {
+            parse_number(&text).unwrap_or(RType::Str(text))
+        }
+        (Some(Affinity::Text), value @ (RType::Num(_) | RType::Real(_))) => {
+            RType::Str(value.to_string())
+        }
+        (_, value) => value,
+    }
+}
+
+// Integers stay integers unless the operation overflows
+fn arithmetic(
+    left: &RType,
+    right: &RType,
+    int_op: fn(i64, i64) -> Option<i64>,
+    real_op: fn(f64, f64) -> f64,
+) -> RType {
+    match (to_numeric(left), to_numeric(right)) {
+        (RType::Num(left), RType::Num(right)) => match int_op(left, right) {
+            Some(value) => RType::Num(value),
+            None => RType::Real(real_op(left as f64, right as f64)),
+        },
+        (left, right) => RType::Real(real_op(to_real(&left), to_real(&right))),
+    }
+}
+
+fn divide(left: &RType, right: &RType) -> RType {
+    match (to_numeric(left), to_numeric(right)) {
+        (_, RType::Num(0)) => RType::Null,
+        (RType::Num(left), RType::Num(right)) => left
+            .checked_div(right)
+            .map_or(RType::Real(left as f64 / right as f64), RType::Num),
+        (left, right) => {
+            let right = to_real(&right);
+            if right == 0.0 {
+                RType::Null
+            } else {
+                RType::Real(to_real(&left) / right)
+            }
+        }
+    }
+}
+
+// Reals are truncated to integers, the result is a real if one operand is a real
+fn modulo(left: &RType, right: &RType) -> RType {
+    let (left, right) = (to_numeric(left), to_numeric(right));
+    let is_real = matches!(left, RType::Real(_)) || matches!(right, RType::Real(_));
+    let (left, right) = (to_real(&left) as i64, to_real(&right) as i64);
+    if right == 0 {
+        return RType::Null;
+    }
+    let value = left.checked_rem(right).unwrap_or(0);
+    if is_real {
+        RType::Real(value as f64)
+    } else {
+        RType::Num(value)
+    }
+}
+
+fn evaluate_function(func: &FuncCall, record: &Record) -> Result<RType> {
+    if func.is_aggregate() {
+        return Err(anyhow!(
+            "misuse of aggregate function {}()",
+            func.function_name
+        ));
+    }
+    let args = match &func.args {
+        FuncArgs::Star => {
+            return Err(anyhow!(
+                "wrong number of arguments to function {}()",
+                func.function_name
+            ));
+        }
+        FuncArgs::List(args) => args
+            .iter()
+            .map(|arg| evaluate(arg, record))
+            .collect::<Result<Vec<_>>>()?,
+    };
+    let value = match (func.function_name.as_str(), args.as_slice()) {
+        ("abs", [value]) => match to_numeric(value) {
+            RType::Num(value) => RType::Num(
+                value
+                    .checked_abs()
+                    .ok_or_else(|| anyhow!("integer overflow"))?,
+            ),
+            RType::Real(value) => RType::Real(value.abs()),
+            value => value,
+        },
+        ("coalesce", args) if args.len() >= 2 => first_not_null(args),
+        ("ifnull", args) if args.len() == 2 => first_not_null(args),
+        ("length", [value]) => match value {
+            RType::Null => RType::Null,
+            RType::Blob(blob) => RType::Num(blob.len() as i64),
+            value => RType::Num(to_text(value).chars().count() as i64),
+        },
+        ("lower", [value]) => map_text(value, str::to_lowercase),
+        // With several arguments, min and max are NULL if an argument is NULL
+        ("min", args) if args.len() >= 2 => extreme(args, Ordering::Less, record.get_encoding()),
+        ("max", args) if args.len() >= 2 => extreme(args, Ordering::Greater, record.get_encoding()),
+        ("upper", [value]) => map_text(value, str::to_uppercase),
+        ("typeof", [value]) => RType::Str(type_name(value).to_string()),
+        (
+            "abs" | "coalesce" | "ifnull" | "length" | "lower" | "max" | "min" | "upper" | "typeof",
+            _,
+        ) => {
+            return Err(anyhow!(
+                "wrong number of arguments to function {}()",
+                func.function_name
+            ));
+        }
+        _ => return Err(anyhow!("no such function: {}", func.function_name)),
+    };
+    Ok(value)
+}
+
+fn first_not_null(args: &[RType]) -> RType {
+    args.iter()
+        .find(|value| !matches!(value, RType::Null))
+        .cloned()
+        .unwrap_or(RType::Null)
+}
+
+// min keeps the values ordered `Less`, max the values ordered `Greater`.
+// Among equal values, like 1 and 1.0, min returns the last one and max the first one.
+fn extreme(args: &[RType], ordering: Ordering, encoding: TextEncoding) -> RType {
+    if args.iter().any(|value| matches!(value, RType::Null)) {
+        return RType::Null;
+    }
+    args.iter()
+        .reduce(
+            |extreme, value| match compare_values(value, extreme, encoding) {
+                Ordering::Equal if ordering == Ordering::Less => value,
+                value_ordering if value_ordering == ordering => value,
+                _ => extreme,
+            },
+        )
+        .cloned()
+        .unwrap_or(RType::Null)
+}
+
+fn map_text(value: &RType, map: fn(&str) -> String) -> RType {
+    match value {
+        RType::Null => RType::Null,
+        value => RType::Str(map(&to_text(value))),
+    }
+}
+
+fn type_name(value: &RType) -> &'static str {
+    match value {
+        RType::Null => "null",
+        RType::Num(_) => "integer",
+        RType::Real(_) => "real",
+        RType::Str(_) => "text",
+        RType::Blob(_) => "blob",
+    }
+}
+
+/// Text representation of a value, used by `||`
+pub fn to_text(value: &RType) -> String {
+    match value {
+        RType::Null => String::new(),
+        RType::Str(value) => value.to_string(),
+        RType::Blob(value) => String::from_utf8_lossy(value).to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Convert a value to a number, NULL stays NULL.
+/// Text is read up to the first character that is not part of a number:
+/// `'12abc'` is 12 and `'abc'` is 0.
+pub fn to_numeric(value: &RType) -> RType {
+    match value {
+        RType::Str(text) => parse_numeric_prefix(text),
+        RType::Blob(blob) => parse_numeric_prefix(&String::from_utf8_lossy(blob)),
+        value => value.clone(),
+    }
+}
+
+pub fn to_real(value: &RType) -> f64 {
+    match to_numeric(value) {
+        RType::Num(value) => value as f64,
+        RType::Real(value) => value,
+        _ => 0.0,
+    }
+}
+
+fn parse_numeric_prefix(text: &str) -> RType {
+    let text = text.trim();
+    let (end, is_real) = numeric_prefix_len(text);
+    parse_number_text(&text[..end], is_real).unwrap_or(RType::Num(0))
+}
+
+// Number written in a text value, None when the text is not only a number
+fn parse_number(text: &str) -> Option<RType> {
+    let text = text.trim();
+    let (end, is_real) = numeric_prefix_len(text);
+    if end < text.len() {
+        return None;
+    }
+    parse_number_text(text, is_real)
+}
+
+fn parse_number_text(number: &str, is_real: bool) -> Option<RType> {
+    if number.is_empty() {
+        return None;
+    }
+    if !is_real && let Ok(value) = number.parse::<i64>() {
+        return Some(RType::Num(value));
+    }
+    number.parse::<f64>().ok().map(RType::Real)
+}
+
+// Length of the number at the start of the text and whether it is a real
+fn numeric_prefix_len(text: &str) -> (usize, bool) {
+    let bytes = text.as_bytes();
+    let mut end = 0;
+    let digits = |end: &mut usize| {
+        let start = *end;
+        while *end < bytes.len() && bytes[*end].is_ascii_digit() {
+            *end += 1;
+        }
+        *end > start
+    };
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let mut has_digits = digits(&mut end);
+    let mut is_real = false;
+    if end < bytes.len() && bytes[end] == b'.' {
+        let mut fraction_end = end + 1;
+        if digits(&mut fraction_end) || has_digits {
+            has_digits = true;
+            is_real = true;
+            end = fraction_end;
+        }
+    }
+    if !has_digits {
+        return (0, false);
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent_end = end + 1;
+        if exponent_end < bytes.len()
+            && (bytes[exponent_end] == b'+' || bytes[exponent_end] == b'-')
+        {
+            exponent_end += 1;
+        }
+        if digits(&mut exponent_end) {
+            is_real = true;
+            end = exponent_end;
+        }
+    }
+    (end, is_real)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_compare_values() {
+        let hello = RType::Str("Hello".to_string());
+        let cases = [
+            (
+                RType::Num(5),
+                BinaryOperator::Eq,
+                hello.clone(),
+                RType::Num(0),
+            ),
+            (
+                RType::Num(5),
+                BinaryOperator::Eq,
+                RType::Num(5),
+                RType::Num(1),
+            ),
+            (
+                RType::Num(5),
+                BinaryOperator::Eq,
+                RType::Real(5.0),
+                RType::Num(1),
+            ),
+            (
+                RType::Num(5),
+                BinaryOperator::LT,
+                hello.clone(),
+                RType::Num(1),
+            ),
+            (
+                hello,
+                BinaryOperator::LT,
+                RType::Blob(vec![0]),
+                RType::Num(1),
+            ),
+            (
+                RType::Num(3),
+                BinaryOperator::GTE,
+                RType::Num(4),
+                RType::Num(0),
+            ),
+            (RType::Null, BinaryOperator::Eq, RType::Null, RType::Null),
+            (
+                RType::Null,
+                BinaryOperator::NotEq,
+                RType::Num(1),
+                RType::Null,
+            ),
+            (RType::Null, BinaryOperator::Is, RType::Null, RType::Num(1)),
+            (
+                RType::Null,
+                BinaryOperator::IsNot,
+                RType::Num(1),
+                RType::Num(1),
+            ),
+            (
+                RType::Num(1),
+                BinaryOperator::IsNot,
+                RType::Num(1),
+                RType::Num(0),
+            ),
+        ];
+        for (left, operator, right, expected) in cases {
+            assert_eq!(
+                compare(
+                    &left,
+                    operator,
+                    &right,
+                    Collation::Binary,
+                    TextEncoding::Utf8
+                ),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_compare_text_in_the_database_encoding() {
+        let text = |text: &str| RType::Str(text.to_string());
+        // U+0100 is 0xC4 0x80 in UTF-8 and 0x00 0x01 in UTF-16LE
+        let (left, right) = (text("\u{100}"), text("b"));
+        assert_eq!(
+            compare(
+                &left,
+                BinaryOperator::GT,
+                &right,
+                Collation::Binary,
+                TextEncoding::Utf8
+            ),
+            RType::Num(1)
+        );
+        assert_eq!(
+            compare(
+                &left,
+                BinaryOperator::GT,
+                &right,
+                Collation::Binary,
+                TextEncoding::Utf16le
+            ),
+            RType::Num(0)
+        );
+        let args = [left, right];
+        assert_eq!(
+            extreme(&args, Ordering::Less, TextEncoding::Utf16le),
+            text("\u{100}")
+        );
+    }
+
+    #[test]
+    fn it_should_keep_the_extreme_value_sqlite_keeps() {
+        // min returns the last of equal values, max the first one
+        let args = [RType::Num(1), RType::Real(1.0)];
+        let encoding = TextEncoding::Utf8;
+        assert!(matches!(
+            extreme(&args, Ordering::Less, encoding),
+            RType::Real(_)
+        ));
+        assert!(matches!(
+            extreme(&args, Ordering::Greater, encoding),
+            RType::Num(_)
+        ));
+    }
+
+    #[test]
+    fn it_should_apply_comparison_affinity() {
+        let text = |text: &str| RType::Str(text.to_string());
+        let numeric = comparison_affinity(Some(Affinity::Integer), None);
+        assert_eq!(
+            apply_comparison_affinity(text(" 12 "), numeric),
+            RType::Num(12)
+        );
+        assert_eq!(
+            apply_comparison_affinity(text("1.5"), numeric),
+            RType::Real(1.5)
+        );
+        assert_eq!(
+            apply_comparison_affinity(text("12abc"), numeric),
+            text("12abc")
+        );
+        let textual = comparison_affinity(None, Some(Affinity::Text));
+        assert_eq!(apply_comparison_affinity(RType::Num(5), textual), text("5"));
+        let blob = comparison_affinity(Some(Affinity::Blob), None);
+        assert_eq!(apply_comparison_affinity(text("5"), blob), text("5"));
+        assert_eq!(
+            comparison_affinity(Some(Affinity::Text), Some(Affinity::Real)),
+            Some(Affinity::Numeric)
+        );
+        assert_eq!(
+            comparison_affinity(Some(Affinity::Text), Some(Affinity::Blob)),
+            None
+        );
+    }
+
+    #[test]
+    fn it_should_compute_arithmetic_like_sqlite() {
+        let cases = [
+            (
+                RType::Num(5),
+                BinaryOperator::Div,
+                RType::Num(2),
+                RType::Num(2),
+            ),
+            (
+                RType::Real(5.0),
+                BinaryOperator::Div,
+                RType::Num(2),
+                RType::Real(2.5),
+            ),
+            (
+                RType::Num(1),
+                BinaryOperator::Div,
+                RType::Num(0),
+                RType::Null,
+            ),
+            (
+                RType::Real(5.5),
+                BinaryOperator::Mod,
+                RType::Num(2),
+                RType::Real(1.0),
+            ),
+            (
+                RType::Num(-7),
+                BinaryOperator::Mod,
+                RType::Num(3),
+                RType::Num(-1),
+            ),
+            (
+                RType::Num(i64::MAX),
+                BinaryOperator::Add,
+                RType::Num(1),
+                RType::Real(i64::MAX as f64 + 1.0),
+            ),
+            (
+                RType::Str("12abc".to_string()),
+                BinaryOperator::Add,
+                RType::Num(1),
+                RType::Num(13),
+            ),
+            (
+                RType::Num(1),
+                BinaryOperator::Concat,
+                RType::Real(2.0),
+                RType::Str("12.0".to_string()),
+            ),
+        ];
+        for (left, operator, right, expected) in cases {
+            assert_eq!(evaluate_binary(left, operator, right), expected);
+        }
+    }
+
+    #[test]
+    fn it_should_use_three_valued_logic() {
+        let null = || Expr::Literal(RType::Null);
+        let num = |value| Expr::Literal(RType::Num(value));
+        let table = Table::from_view_columns("t", vec![]);
+        let record = Record::from_values(vec![], &table, TextEncoding::Utf8);
+        let cases = [
+            (null(), BinaryOperator::And, num(0), RType::Num(0)),
+            (null(), BinaryOperator::And, num(1), RType::Null),
+            (null(), BinaryOperator::Or, num(1), RType::Num(1)),
+            (null(), BinaryOperator::Or, num(0), RType::Null),
+            (num(1), BinaryOperator::And, num(2), RType::Num(1)),
+            (num(0), BinaryOperator::Or, num(0), RType::Num(0)),
+        ];
+        for (left, operator, right, expected) in cases {
+            let expr = Expr::binary(left, operator, right);
+            assert_eq!(evaluate(&expr, &record).unwrap(), expected, "{}", expr);
+        }
+        let not_null = Expr::Unary {
+            operator: UnaryOperator::Not,
+            expr: Box::new(null()),
+        };
+        assert_eq!(evaluate(&not_null, &record).unwrap(), RType::Null);
+    }
+
+    #[test]
+    fn it_should_evaluate_in_lists_and_between_with_nulls() {
+        let table = Table::from_view_columns("t", vec![]);
+        let record = Record::from_values(vec![], &table, TextEncoding::Utf8);
+        let null = || Expr::Literal(RType::Null);
+        let num = |value| Expr::Literal(RType::Num(value));
+        let in_list = |expr, list, negated| Expr::InList {
+            expr: Box::new(expr),
+            list,
+            negated,
+        };
+        let between = |expr, low, high, negated| Expr::Between {
+            expr: Box::new(expr),
+            low: Box::new(low),
+            high: Box::new(high),
+            negated,
+        };
+        let cases = [
+            (in_list(num(1), vec![num(2), num(1)], false), RType::Num(1)),
+            (in_list(num(1), vec![num(2), null()], false), RType::Null),
+            (in_list(num(1), vec![num(1), null()], true), RType::Num(0)),
+            (in_list(num(3), vec![num(2)], true), RType::Num(1)),
+            (in_list(null(), vec![], false), RType::Num(0)),
+            (in_list(null(), vec![num(1)], true), RType::Null),
+            (between(num(2), num(1), num(3), false), RType::Num(1)),
+            (between(num(2), num(3), null(), false), RType::Num(0)),
+            (between(num(2), num(1), null(), false), RType::Null),
+            (between(num(4), num(1), num(3), true), RType::Num(1)),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(evaluate(&expr, &record).unwrap(), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn it_should_short_circuit_logical_operators() {
+        let table = Table::from_view_columns("t", vec![]);
+        let record = Record::from_values(vec![], &table, TextEncoding::Utf8);
+        // The unknown column would be an error if the right operand was evaluated
+        let missing = || Expr::Column("missing".to_string());
+        let expr = Expr::binary(Expr::Literal(RType::Num(0)), BinaryOperator::And, missing());
+        assert_eq!(evaluate(&expr, &record).unwrap(), RType::Num(0));
+        let expr = Expr::binary(Expr::Literal(RType::Num(1)), BinaryOperator::Or, missing());
+        assert_eq!(evaluate(&expr, &record).unwrap(), RType::Num(1));
+    }
+
+    #[test]
+    fn it_should_convert_text_prefix_to_numbers() {
+        assert_eq!(to_numeric(&RType::Str(" 12 ".to_string())), RType::Num(12));
+        assert_eq!(
+            to_numeric(&RType::Str("1e2x".to_string())),
+            RType::Real(100.0)
+        );
+        assert_eq!(to_numeric(&RType::Str(".5".to_string())), RType::Real(0.5));
+        assert_eq!(to_numeric(&RType::Str("0x10".to_string())), RType::Num(0));
+        assert_eq!(to_numeric(&RType::Str("abc".to_string())), RType::Num(0));
+    }
+}
@@ -0,0 +1,656 @@
+//! Joins of the tables of a FROM clause.
+//!
+//! The rows of a join are records of a table made of the columns of every joined
+//! table, named `qualifier.column`: the column references of the query are resolved
+//! to these names first.
+//!
+//! Tables are joined with nested loops: for each row of the tables on its left, the rows
+//! of a table are read and checked against the join constraint. The rows of the first
+//! table are read one by one, with the plan the where clause allows for a single table.
+//! When the constraint, or the where clause for inner joins, compares a column of another
+//! table with an expression on the tables on its left, the rows are found with a rowid or
+//! an index seek. Otherwise the rows of the table are read the first time they are needed
+//! and kept in memory.
+//! A row without match in a LEFT JOIN is joined with NULLs.
+//!
+//! See [The FROM clause](https://www.sqlite.org/lang_select.html#the_from_clause)
+use crate::db::DB;
+use crate::db::cursor::{IndexCursor, TableCursor};
+use crate::db::fileformat::record::Record;
+use crate::db::table::Table;
+use crate::executor::collation::Collation;
+use crate::executor::db_response::{RType, Response};
+use crate::executor::eval::{apply_comparison_affinity, comparison_affinity, evaluate, is_true};
+use crate::parser::create_table::Affinity;
+use crate::parser::expr::{BinaryOperator, Expr};
+use crate::parser::from_clause::{Join, JoinConstraint};
+use crate::parser::select::{SelectItem, SelectStatement};
+use crate::parser::where_clause::Where;
+use anyhow::{Result, anyhow};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A table of the FROM clause
+pub struct JoinSource {
+    /// Alias or name qualifying the columns of the table
+    pub qualifier: String,
+    pub table: Rc<Table>,
+    /// Rows of a view, computed before the query
+    pub rows: Option<Response>,
+    /// How the table is joined to the tables on its left, None for the first table
+    pub join: Option<Join>,
+}
+
+/// Resolves the column references of a query to the columns of the FROM clause tables.
+///
+/// With a single table, `table.column` is the column of the table. With a join, each
+/// column is named `qualifier.column` and an unqualified name must be a column of one
+/// table only.
+pub struct ColumnResolver<'s> {
+    sources: &'s [JoinSource],
+    // Columns of each table merged with a column of a table on its left by USING or
+    // NATURAL. An unqualified name refers to the left column.
+    merged: Vec<Vec<String>>,
+    // Condition joining each table to the tables on its left
+    conditions: Vec<Option<Expr>>,
+}
+
+impl<'s> ColumnResolver<'s> {
+    pub fn new(sources: &'s [JoinSource]) -> Result<Self> {
+        let mut resolver = Self {
+            sources,
+            merged: vec![vec![]; sources.len()],
+            conditions: vec![],
+        };
+        // USING and NATURAL columns are merged first, so the ON constraints
+        // resolve their names like the rest of the query
+        let mut using_conditions = vec![];
+        for (i, source) in sources.iter().enumerate() {
+            using_conditions.push(resolver.merge_columns(i, source)?);
+        }
+        for (source, using_condition) in sources.iter().zip(using_conditions) {
+            let on_condition = match source
+                .join
+                .as_ref()
+                .and_then(|join| join.constraint.as_ref())
+            {
+                Some(JoinConstraint::On(expr)) => Some(resolver.resolve_expr(expr)?),
+                _ => None,
+            };
+            let condition = using_condition
+                .into_iter()
+                .chain(on_condition)
+                .reduce(|left, right| Expr::binary(left, BinaryOperator::And, right));
+            resolver.conditions.push(condition);
+        }
+        Ok(resolver)
+    }
+
+    // Merge the USING or NATURAL columns of the table at `index` with the columns of the
+    // tables on its left. Returns the condition comparing them.
+    fn merge_columns(&mut self, index: usize, source: &JoinSource) -> Result<Option<Expr>> {
+        let Some(join) = &source.join else {
+            return Ok(None);
+        };
+        let columns = match &join.constraint {
+            // The columns with the same name in both sides
+            _ if join.natural => source
+                .table
+                .cols_name
+                .iter()
+                .filter(|col_name| self.find_left_source(index, col_name).is_some())
+                .cloned()
+                .collect(),
+            Some(JoinConstraint::Using(columns)) => columns.clone(),
+            _ => vec![],
+        };
+        let mut condition = None;
+        for col_name in columns {
+            let left = self.find_left_source(index, &col_name);
+            let (Some(left), Some(_)) = (left, source.table.get_column_index(&col_name)) else {
+                return Err(anyhow!(
+                    "cannot join using column {} - column not present in both tables",
+                    col_name
+                ));
+            };
+            let equality = Expr::binary(
+                Expr::Column(self.column_name(&self.sources[left], &col_name)),
+                BinaryOperator::Eq,
+                Expr::Column(self.column_name(source, &col_name)),
+            );
+            condition = Some(match condition {
+                Some(condition) => Expr::binary(condition, BinaryOperator::And, equality),
+                None => equality,
+            });
+            self.merged[index].push(col_name);
+        }
+        Ok(condition)
+    }
+
+    // First table on the left of the table at `index` with the column
+    fn find_left_source(&self, index: usize, col_name: &str) -> Option<usize> {
+        (0..index).find(|&i| self.is_visible(i, col_name))
+    }
+
+    // True when an unqualified name can refer to the column of the table at `index`
+    fn is_visible(&self, index: usize, col_name: &str) -> bool {
+        self.sources[index]
+            .table
+            .get_column_index(col_name)
+            .is_some()
+            && !self.merged[index].iter().any(|merged| merged == col_name)
+    }
+
+    /// Condition joining each table to the tables on its left, None for the first table
+    /// and for tables joined without constraint
+    pub fn into_conditions(self) -> Vec<Option<Expr>> {
+        self.conditions
+    }
+
+    fn is_join(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    fn column_name(&self, source: &JoinSource, col_name: &str) -> String {
+        if self.is_join() {
+            format!("{}.{}", source.qualifier, col_name)
+        } else {
+            col_name.to_string()
+        }
+    }
+
+    fn find_source(&self, qualifier: &str) -> Option<&JoinSource> {
+        self.sources
+            .iter()
+            .find(|source| source.qualifier.eq_ignore_ascii_case(qualifier))
+    }
+
+    // Name of the column of the records a column reference refers to.
+    // Returns None when the name is not a column of the tables, it can be an alias.
+    fn resolve_column(&self, name: &str) -> Result<Option<String>> {
+        if let Some((qualifier, col_name)) = name.split_once('.') {
+            return Ok(self
+                .find_source(qualifier)
+                .filter(|source| {
+                    source.table.get_column_index(col_name).is_some()
+                        || source.table.is_rowid_column(col_name)
+                })
+                .map(|source| self.column_name(source, col_name)));
+        }
+        if !self.is_join() {
+            return Ok(None);
+        }
+        let mut sources = (0..self.sources.len()).filter(|&i| self.is_visible(i, name));
+        let Some(first) = sources.next() else {
+            return Ok(None);
+        };
+        if sources.next().is_some() {
+            return Err(anyhow!("ambiguous column name: {}", name));
+        }
+        Ok(Some(self.column_name(&self.sources[first], name)))
+    }
+
+    fn resolve_expr(&self, expr: &Expr) -> Result<Expr> {
+        let mut names = HashMap::new();
+        for name in expr.get_columns() {
+            if let Some(resolved) = self.resolve_column(name)? {
+                names.insert(name, resolved);
+            }
+        }
+        Ok(expr.transform(&|expr| match expr {
+            Expr::Column(name) => names
+                .get(name.as_str())
+                .map(|resolved| Expr::Column(resolved.clone())),
+            _ => None,
+        }))
+    }
+
+    // Result columns of `*` or `table.*`, the columns of a join are listed one by one
+    fn expand_star(&self, qualifier: Option<&str>) -> Result<Vec<SelectItem>> {
+        let source = match qualifier {
+            Some(qualifier) => Some(
+                self.find_source(qualifier)
+                    .ok_or_else(|| anyhow!("no such table: {}", qualifier))?,
+            ),
+            None => None,
+        };
+        if !self.is_join() {
+            return Ok(vec![SelectItem::Star]);
+        }
+        let mut items = vec![];
+        for (i, other) in self.sources.iter().enumerate() {
+            if source.is_some_and(|source| !std::ptr::eq(source, other)) {
+                continue;
+            }
+            for col_name in other.table.cols_name.iter() {
+                // `*` lists the USING and NATURAL columns once
+                if source.is_none() && !self.is_visible(i, col_name) {
+                    continue;
+                }
+                items.push(SelectItem::Expr {
+                    expr: Expr::Column(self.column_name(other, col_name)),
+                    alias: None,
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    /// Copy of the query where the column references are the columns of the records.
+    /// An ORDER BY term naming a result column alias is kept as it is.
+    pub fn resolve_query(&self, query: &SelectStatement) -> Result<SelectStatement> {
+        let mut resolved = query.clone();
+        let mut items = vec![];
+        for item in query.select_clause.items.iter() {
+            match item {
+                SelectItem::Star => items.extend(self.expand_star(None)?),
+                SelectItem::TableStar(qualifier) if self.is_join() => {
+                    items.extend(self.expand_star(Some(qualifier))?)
+                }
+                SelectItem::TableStar(qualifier) => {
+                    self.expand_star(Some(qualifier))?;
+                    items.push(item.clone());
+                }
+                SelectItem::Expr { expr, alias } => items.push(SelectItem::Expr {
+                    expr: self.resolve_expr(expr)?,
+                    alias: alias.clone(),
+                }),
+            }
+        }
+        resolved.select_clause.items = items;
+        if let Some(where_clause) = &query.where_clause {
+            resolved.where_clause = Some(Where::new(self.resolve_expr(&where_clause.expr)?));
+        }
+        resolved.group_by = query
+            .group_by
+            .iter()
+            .map(|expr| self.resolve_expr(expr))
+            .collect::<Result<_>>()?;
+        if let Some(having) = &query.having {
+            resolved.having = Some(self.resolve_expr(having)?);
+        }
+        for term in resolved.order_by.iter_mut() {
+            let is_alias = matches!(&term.expr, Expr::Column(name) if has_alias(query, name));
+            if !is_alias {
+                term.expr = self.resolve_expr(&term.expr)?;
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+fn has_alias(query: &SelectStatement, name: &str) -> bool {
+    query.select_clause.items.iter().any(|item| {
+        matches!(item, SelectItem::Expr { alias: Some(alias), .. } if alias.eq_ignore_ascii_case(name))
+    })
+}
+
+/// How the rows of a joined table are read, for each row of the tables on its left
+enum JoinScan {
+    /// Rows of a view, computed before the query
+    Rows(Response),
+    /// Rows of a table, read once when the table is first reached
+    Table(OnceCell<Response>),
+    /// The row whose rowid is the value of an expression on the tables on the left
+    Rowid {
+        value: Expr,
+        affinity: Option<Affinity>,
+    },
+    /// The rows whose indexed column is equal to the value of an expression
+    /// on the tables on the left
+    Index {
+        index_root: usize,
+        value: Expr,
+        affinity: Option<Affinity>,
+    },
+}
+
+/// A table of a join, with how its rows are read and matched
+pub struct JoinLevel {
+    table: Rc<Table>,
+    scan: JoinScan,
+    condition: Option<Expr>,
+    is_left: bool,
+}
+
+impl JoinLevel {
+    fn read_rows<'l>(&'l self, db: &DB, record: &Record) -> Result<Cow<'l, [Vec<RType>]>> {
+        let rowids = match &self.scan {
+            JoinScan::Rows(rows) => return Ok(Cow::Borrowed(rows)),
+            JoinScan::Table(rows) => {
+                if let Some(rows) = rows.get() {
+                    return Ok(Cow::Borrowed(rows));
+                }
+                let table_rows = read_table(db, &self.table)?;
+                return Ok(Cow::Borrowed(rows.get_or_init(|| table_rows)));
+            }
+            JoinScan::Rowid { value, affinity } => {
+                let value = apply_comparison_affinity(evaluate(value, record)?, *affinity);
+                match value {
+                    RType::Num(rowid) => vec![rowid],
+                    RType::Real(real) if real.fract() == 0.0 => vec![real as i64],
+                    _ => vec![],
+                }
+            }
+            JoinScan::Index {
+                index_root,
+                value,
+                affinity,
+            } => {
+                // NULL is never equal to a value
+                match apply_comparison_affinity(evaluate(value, record)?, *affinity) {
+                    RType::Null => vec![],
+                    value => IndexCursor::new(db, *index_root)?.find_rowids(&value)?,
+                }
+            }
+        };
+        let mut cursor = TableCursor::new(db, self.table.get_root_page())?;
+        let mut rows = vec![];
+        for rowid in rowids {
+            if let Some(record) = cursor.find(rowid, &self.table)? {
+                rows.push(record.get_values());
+            }
+        }
+        Ok(Cow::Owned(rows))
+    }
+}
+
+/// Where clause of the first table of a join, where its columns are named like in the
+/// table. It chooses how the rows of the first table are read.
+pub fn get_outer_where(where_clause: &Where, qualifier: &str) -> Where {
+    let prefix = format!("{}.", qualifier);
+    Where::new(where_clause.expr.transform(&|expr| {
+        match expr {
+            Expr::Column(name) => name
+                .strip_prefix(&prefix)
+                .map(|col_name| Expr::Column(col_name.to_string())),
+            _ => None,
+        }
+    }))
+}
+
+/// Choose how the rows of the tables after the first one are read.
+/// `table` is the table of the join records, `conditions` are the conditions joining
+/// each table to the tables on its left, `start` is the number of columns of the first table.
+pub fn plan_join(
+    db: &DB,
+    sources: Vec<JoinSource>,
+    conditions: Vec<Option<Expr>>,
+    where_clause: Option<&Where>,
+    table: &Table,
+    start: usize,
+) -> Result<Vec<JoinLevel>> {
+    let mut levels = vec![];
+    // Position of the first column of the table in the join records
+    let mut start = start;
+    for (source, condition) in sources.into_iter().zip(conditions) {
+        let is_left = source.join.as_ref().is_some_and(Join::is_left);
+        // The where clause cannot choose the rows of a LEFT JOIN table:
+        // the rows without match are joined with NULLs before it is checked
+        let mut equalities = condition
+            .iter()
+            .flat_map(Expr::get_conditions)
+            .collect::<Vec<_>>();
+        if !is_left && let Some(where_clause) = where_clause {
+            equalities.extend(where_clause.expr.get_conditions());
+        }
+        let seek = equalities
+            .into_iter()
+            .find_map(|equality| plan_seek(db, &source, equality, table, start));
+        let scan = match (seek, source.rows) {
+            (Some(seek), _) => seek,
+            (None, Some(rows)) => JoinScan::Rows(rows),
+            (None, None) => JoinScan::Table(OnceCell::new()),
+        };
+        start += source.table.cols_name.len();
+        levels.push(JoinLevel {
+            table: source.table,
+            scan,
+            condition,
+            is_left,
+        });
+    }
+    Ok(levels)
+}
+
+// A seek answers `column = value` when the column is the rowid alias or the first column
+// of an index of the table, and the value only depends on the tables on its left
+fn plan_seek(
+    db: &DB,
+    source: &JoinSource,
+    equality: &Expr,
+    table: &Table,
+    start: usize,
+) -> Option<JoinScan> {
+    // Index entries of WITHOUT ROWID tables hold the primary key instead of the rowid
+    if source.rows.is_some() || source.table.is_without_rowid() {
+        return None;
+    }
+    let Expr::Binary {
+        left,
+        operator: BinaryOperator::Eq,
+        right,
+    } = equality
+    else {
+        return None;
+    };
+    // Seeks find the values equal with the BINARY collation
+    if Collation::for_comparison(left, right, table).ok()? != Collation::Binary {
+        return None;
+    }
+    let is_on_left_tables = |expr: &Expr| {
+        !expr.contains_aggregate()
+            && expr.get_columns().into_iter().all(|name| {
+                table
+                    .get_column_index(name)
+                    .is_some_and(|index| index < start)
+            })
+    };
+    let (column, value) =
+        [(left, right), (right, left)]
+            .into_iter()
+            .find_map(|(column, value)| match column.as_ref() {
+                Expr::Column(name) if is_on_left_tables(value) => {
+                    let index = table.get_column_index(name)?;
+                    let index = index.checked_sub(start)?;
+                    (index < source.table.cols_name.len()).then_some((index, value.as_ref()))
+                }
+                _ => None,
+            })?;
+    let column_affinity = source.table.get_column_affinity(column);
+    let value_affinity = match value {
+        Expr::Column(name) => table
+            .get_column_index(name)
+            .map(|index| table.get_column_affinity(index)),
+        _ => None,
+    };
+    // The value is converted like the evaluator does, so `a.id = b.text_id` seeks the rowid
+    let affinity = comparison_affinity(Some(column_affinity), value_affinity);
+    if source.table.is_rowid_alias(column) {
+        return Some(JoinScan::Rowid {
+            value: value.clone(),
+            affinity,
+        });
+    }
+    // The index holds the values converted to the column affinity, the comparison
+    // must not convert them again
+    let keeps_index_values = match affinity {
+        None => true,
+        Some(affinity) => {
+            affinity == column_affinity || (affinity.is_numeric() && column_affinity.is_numeric())
+        }
+    };
+    let col_name = source.table.get_column_name(column);
+    let index_root = db.metadata.find_index(source.table.get_name(), col_name)?;
+    keeps_index_values.then(|| JoinScan::Index {
+        index_root,
+        value: value.clone(),
+        affinity,
+    })
+}
+
+fn read_table(db: &DB, table: &Table) -> Result<Response> {
+    let mut rows = vec![];
+    if table.is_without_rowid() {
+        let mut cursor = IndexCursor::new(db, table.get_root_page())?;
+        while let Some(record) = cursor.next_record(table)? {
+            rows.push(record.get_values());
+        }
+    } else {
+        let mut cursor = TableCursor::new(db, table.get_root_page())?;
+        while let Some(record) = cursor.next_record(table)? {
+            rows.push(record.get_values());
+        }
+    }
+    Ok(rows)
+}
+
+/// Join a row of the first table to the rows of the other tables.
+/// `process` returns false when the scan can stop, and so does this function.
+pub fn scan<'t>(
+    db: &DB,
+    levels: &[JoinLevel],
+    table: &'t Table,
+    mut values: Vec<RType>,
+    process: &mut dyn FnMut(Record<'t>) -> Result<bool>,
+) -> Result<bool> {
+    scan_level(db, levels, table, &mut values, process)
+}
+
+// Join the rows of the first level to `values`, the row of the tables on its left.
+// Returns false when the scan can stop.
+fn scan_level<'t>(
+    db: &DB,
+    levels: &[JoinLevel],
+    table: &'t Table,
+    values: &mut Vec<RType>,
+    process: &mut dyn FnMut(Record<'t>) -> Result<bool>,
+) -> Result<bool> {
+    let encoding = db.text_encoding;
+    let Some((level, inner_levels)) = levels.split_first() else {
+        return process(Record::from_values(values.clone(), table, encoding));
+    };
+    let start = values.len();
+    let rows = level.read_rows(db, &Record::from_values(values.clone(), table, encoding))?;
+    let mut has_match = false;
+    for row in rows.iter() {
+        values.extend(row.iter().cloned());
+        let is_match = match &level.condition {
+            Some(condition) => is_true(&evaluate(
+                condition,
+                &Record::from_values(values.clone(), table, encoding),
+            )?),
+            None => true,
+        };
+        has_match |= is_match;
+        let keep_scanning = !is_match || scan_level(db, inner_levels, table, values, process)?;
+        values.truncate(start);
+        if !keep_scanning {
+            return Ok(false);
+        }
+    }
+    if level.is_left && !has_match {
+        values.extend(std::iter::repeat_n(
+            RType::Null,
+            level.table.cols_name.len(),
+        ));
+        let keep_scanning = scan_level(db, inner_levels, table, values, process)?;
+        values.truncate(start);
+        return Ok(keep_scanning);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, statement::Statement};
+
+    fn parse_select(query: &str) -> SelectStatement {
+        match Parser::new(query).next() {
+            Some(Ok(Statement::Select(select))) => select,
+            _ => panic!("Expected a select statement"),
+        }
+    }
+
+    fn get_sources(query: &SelectStatement) -> Vec<JoinSource> {
+        let from_clause = query.from_clause.as_ref().unwrap();
+        from_clause
+            .get_tables()
+            .enumerate()
+            .map(|(i, table_ref)| {
+                let cols_name = match table_ref.name.as_str() {
+                    "apples" => vec!["id".into(), "name".into(), "color".into()],
+                    _ => vec!["id".into(), "apple_id".into(), "color".into()],
+                };
+                JoinSource {
+                    qualifier: table_ref.get_qualifier().to_string(),
+                    table: Rc::new(Table::from_view_columns(&table_ref.name, cols_name)),
+                    rows: None,
+                    join: i.checked_sub(1).map(|i| from_clause.joins[i].clone()),
+                }
+            })
+            .collect()
+    }
+
+    fn resolve(query: &str) -> Result<(String, Vec<Option<String>>)> {
+        let query = parse_select(query);
+        let sources = get_sources(&query);
+        let resolver = ColumnResolver::new(&sources)?;
+        let resolved = resolver.resolve_query(&query)?;
+        let conditions = resolver
+            .into_conditions()
+            .into_iter()
+            .map(|condition| condition.map(|condition| condition.to_string()))
+            .collect();
+        Ok((resolved.to_string(), conditions))
+    }
+
+    #[test]
+    fn it_should_strip_the_qualifier_of_a_single_table() {
+        let (query, _) = resolve("SELECT a.name FROM apples AS a WHERE apples.id = 1").unwrap();
+        assert_eq!(query, "SELECT name FROM apples AS a WHERE apples.id = 1");
+    }
+
+    #[test]
+    fn it_should_qualify_the_columns_of_a_join() {
+        let (query, conditions) =
+            resolve("SELECT name, s.* FROM apples a JOIN seeds s ON apple_id = a.id").unwrap();
+        assert_eq!(
+            query,
+            "SELECT a.name, s.id, s.apple_id, s.color FROM apples AS a JOIN seeds AS s ON apple_id = a.id"
+        );
+        assert_eq!(
+            conditions,
+            vec![None, Some("s.apple_id = a.id".to_string())]
+        );
+        assert_eq!(
+            resolve("SELECT color FROM apples, seeds")
+                .unwrap_err()
+                .to_string(),
+            "ambiguous column name: color"
+        );
+    }
+
+    #[test]
+    fn it_should_merge_using_and_natural_columns() {
+        let (query, conditions) =
+            resolve("SELECT * FROM apples NATURAL JOIN seeds ORDER BY color").unwrap();
+        assert_eq!(
+            query,
+            "SELECT apples.id, apples.name, apples.color, seeds.apple_id FROM apples NATURAL JOIN seeds ORDER BY apples.color"
+        );
+        assert_eq!(
+            conditions[1].as_deref(),
+            Some("apples.id = seeds.id AND apples.color = seeds.color")
+        );
+        assert_eq!(
+            resolve("SELECT * FROM apples JOIN seeds USING (name)")
+                .unwrap_err()
+                .to_string(),
+            "cannot join using column name - column not present in both tables"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Pattern matching of the LIKE, ILIKE and GLOB operators.
+//!
+//! * LIKE: `%` matches any sequence of characters, `_` matches one character.
+//!   Letters are compared without case for ASCII characters only, like sqlite does.
+//! * ILIKE: LIKE comparing all letters without case, it is not supported by sqlite.
+//! * GLOB: `*` matches any sequence of characters, `?` matches one character and
+//!   `[...]` matches one character of a set. Letters are compared with case.
+//!
+//! See [LIKE and GLOB](https://www.sqlite.org/lang_expr.html#like)
+
+/// Returns true if `text` matches the LIKE `pattern`.
+/// A character following `escape` in the pattern matches itself.
+pub fn like(text: &str, pattern: &str, escape: Option<char>, ignore_case: bool) -> bool {
+    let (text, pattern, escape) = if ignore_case {
+        (
+            text.to_lowercase(),
+            pattern.to_lowercase(),
+            escape.map(|escape| escape.to_lowercase().next().unwrap_or(escape)),
+        )
+    } else {
+        (text.to_string(), pattern.to_string(), escape)
+    };
+    let text = text.chars().collect::<Vec<_>>();
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    like_chars(&text, &pattern, escape)
+}
+
+fn like_chars(text: &[char], pattern: &[char], escape: Option<char>) -> bool {
+    let (mut t, mut p) = (0, 0);
+    while p < pattern.len() {
+        match pattern[p] {
+            c if Some(c) == escape => {
+                // An escape at the end of the pattern matches nothing
+                let Some(&literal) = pattern.get(p + 1) else {
+                    return false;
+                };
+                if t >= text.len() || !text[t].eq_ignore_ascii_case(&literal) {
+                    return false;
+                }
+                t += 1;
+                p += 2;
+            }
+            '%' => {
+                // Wildcards following `%` only require a minimum number of characters
+                while p < pattern.len() && (pattern[p] == '%' || pattern[p] == '_') {
+                    if pattern[p] == '_' {
+                        if t >= text.len() {
+                            return false;
+                        }
+                        t += 1;
+                    }
+                    p += 1;
+                }
+                if p == pattern.len() {
+                    return true;
+                }
+                return (t..=text.len())
+                    .any(|start| like_chars(&text[start..], &pattern[p..], escape));
+            }
+            '_' => {
+                if t >= text.len() {
+                    return false;
+                }
+                t += 1;
+                p += 1;
+            }
+            c => {
+                if t >= text.len() || !text[t].eq_ignore_ascii_case(&c) {
+                    return false;
+                }
+                t += 1;
+                p += 1;
+            }
+        }
+    }
+    t == text.len()
+}
+
+/// Returns true if `text` matches the GLOB `pattern`.
+pub fn glob(text: &str, pattern: &str) -> bool {
+    let text = text.chars().collect::<Vec<_>>();
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    glob_chars(&text, &pattern)
+}
+
+fn glob_chars(text: &[char], pattern: &[char]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    while p < pattern.len() {
+        match pattern[p] {
+            '*' => {
+                while p < pattern.len() && (pattern[p] == '*' || pattern[p] == '?') {
+                    if pattern[p] == '?' {
+                        if t >= text.len() {
+                            return false;
+                        }
+                        t += 1;
+                    }
+                    p += 1;
+                }
+                if p == pattern.len() {
+                    return true;
+                }
+                return (t..=text.len()).any(|start| glob_chars(&text[start..], &pattern[p..]));
+            }
+            '?' => {
+                if t >= text.len() {
+                    return false;
+                }
+                t += 1;
+                p += 1;
+            }
+            '[' => {
+                let Some(&c) = text.get(t) else {
+                    return false;
+                };
+                let Some((matched, set_len)) = match_set(c, &pattern[p + 1..]) else {
+                    // A set that is not closed matches nothing
+                    return false;
+                };
+                if !matched {
+                    return false;
+                }
+                t += 1;
+                p += set_len + 1;
+            }
+            c => {
+                if text.get(t) != Some(&c) {
+                    return false;
+                }
+                t += 1;
+                p += 1;
+            }
+        }
+    }
+    t == text.len()
+}
+
+// Match a character against the set following `[`.
+// Returns whether it matches and the length of the set, `]` included.
+// `[^...]` matches the characters that are not in the set, a `]` right after
+// `[` or `[^` is part of the set and `a-z` is a range.
+fn match_set(c: char, set: &[char]) -> Option<(bool, usize)> {
+    let mut i = 0;
+    let negated = set.first() == Some(&'^');
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let &start = set.get(i)?;
+        if start == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if set.get(i + 1) == Some(&'-')
+            && let Some(&end) = set.get(i + 2)
+            && end != ']'
+        {
+            matched |= start <= c && c <= end;
+            i += 3;
+        } else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_match_like_patterns() {
+        let cases = [
+            ("hello", "h%o", None, true),
+            ("hello", "H_LLO", None, true),
+            ("hello", "h_o", None, false),
+            ("hello", "%", None, true),
+            ("", "%", None, true),
+            ("", "_", None, false),
+            ("hello", "%l%_", None, true),
+            ("hello", "%x%", None, false),
+            ("100%", "100!%", Some('!'), true),
+            ("1000", "100!%", Some('!'), false),
+            ("a_c", "a!_c", Some('!'), true),
+            ("abc", "a!_c", Some('!'), false),
+            ("Élan", "élan", None, false),
+        ];
+        for (text, pattern, escape, expected) in cases {
+            assert_eq!(
+                like(text, pattern, escape, false),
+                expected,
+                "{} LIKE {}",
+                text,
+                pattern
+            );
+        }
+        assert!(like("Élan", "élan", None, true));
+    }
+
+    #[test]
+    fn it_should_match_glob_patterns() {
+        let cases = [
+            ("hello", "h*o", true),
+            ("hello", "H*", false),
+            ("hello", "h?llo", true),
+            ("hello", "h[a-f]llo", true),
+            ("hello", "h[^e]llo", false),
+            ("h]llo", "h[]]llo", true),
+            ("h-llo", "h[a-]llo", true),
+            ("hello", "h[ello", false),
+            ("hello", "*l?", true),
+            ("", "*", true),
+        ];
+        for (text, pattern, expected) in cases {
+            assert_eq!(glob(text, pattern), expected, "{} GLOB {}", text, pattern);
+        }
+    }
+}
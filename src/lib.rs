@@ -23,17 +23,16 @@
 //!
 //! # Example
 //! ```no_run
-//!    fn main() {
-//!    let db = match DB::new("sample.db") {
-//!        Ok(db) => db,
-//!        Err(error) => eprintln!("Impossible to read database metadata: {}", error),
-//!    };
-//!    let mut executor = Executor::new(db);
-//!    if let Some(response) = executor.execute(command).unwrap() {
-//!        display_response(&response);
-//!    }
-//!    }
-//!```
+//! use codecrafters_sqlite::{db::DB, executor::Executor};
+//!
+//! let db = DB::new("sample.db").expect("Impossible to read database metadata");
+//! let mut executor = Executor::new(db);
+//! for (_statement, response) in executor.execute("SELECT name FROM apples").unwrap() {
+//!     for row in response {
+//!         println!("{:?}", row);
+//!     }
+//! }
+//! ```
 
 pub mod db;
 pub mod executor;
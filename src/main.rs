@@ -38,7 +38,7 @@ fn display_response(responses: &[(Statement, Response)]) {
                 }
                 print!("{}", col);
             }
-            println!("");
+            println!();
         }
     }
 }
@@ -1,20 +1,32 @@
 //! Internal module to parse SQL and build queries.
-//! It supports SELECT and FROM clauses.
+//! It supports SELECT, FROM and WHERE clauses with expressions, and the CREATE TABLE and CREATE INDEX
+//! statements stored in the sqlite_schema table.
 //!
 //! # Example
 //! ```
-//! use crate::parser::Parser;
+//! use codecrafters_sqlite::parser::Parser;
 //! let query_str = "SELECT name, age FROM users;";
-//! let mut parser = Parser::new(query_str);
+//! let parser = Parser::new(query_str);
 //! for query in parser {
 //!    let query = query.unwrap();
 //!    println!("{}", query);
 //!    }
 //! ```
+use crate::executor::db_response::RType;
 use crate::parser::{
-    function::FuncCall,
-    identifier::{Identifier, VType},
-    select::{SelectClause, SelectItem, SelectStatement},
+    create_index::CreateIndexStatement,
+    create_table::{
+        ColumnConstraint, ColumnDef, CreateTableStatement, DefaultValue, ForeignKey, IndexedColumn,
+        SortOrder, TableConstraint,
+    },
+    create_view::CreateViewStatement,
+    expr::{
+        BinaryOperator, COLLATE_PRECEDENCE, EQUALITY_PRECEDENCE, Expr, LikeOperator, UnaryOperator,
+    },
+    from_clause::{FromClause, Join, JoinConstraint, JoinOperator, TableRef},
+    function::{FuncArgs, FuncCall},
+    order_by::{NullsOrder, OrderingTerm},
+    select::{Limit, SelectClause, SelectItem, SelectStatement},
     statement::Statement,
     token::Token,
     tokenizer::Tokenizer,
@@ -23,8 +35,13 @@ use crate::parser::{
 use anyhow::{Result, anyhow};
 use std::iter::Iterator;
 
+pub mod create_index;
+pub mod create_table;
+pub mod create_view;
+pub mod expr;
+pub mod from_clause;
 pub mod function;
-pub mod identifier;
+pub mod order_by;
 pub mod select;
 pub mod statement;
 pub mod token;
@@ -45,18 +62,16 @@ impl<'a> Parser<'a> {
     //// if not, returns an error
     //// If there is no next token, return None
     fn expect_token(&mut self, expected_token: Token) -> Result<Token> {
-        if let Some(Ok(next)) = self.tokenizer.next() {
-            if next == expected_token {
-                return Ok(next);
-            } else {
-                return Err(anyhow!(
-                    "Expected token {} but got {}",
-                    expected_token,
-                    next
-                ));
-            }
+        match self.tokenizer.next() {
+            Some(Ok(next)) if next == expected_token => Ok(next),
+            Some(Ok(next)) => Err(anyhow!(
+                "Expected token {} but got {}",
+                expected_token,
+                next
+            )),
+            Some(Err(err)) => Err(err),
+            None => Err(anyhow!("Expected token {} but got EOF", expected_token)),
         }
-        Err(anyhow!("Expected token {} but got EOF", expected_token))
     }
 
     /// Peek the next token and check if it matches the expected token
@@ -78,17 +93,82 @@ impl<'a> Parser<'a> {
         Err(anyhow!("Expected token {} but got EOF", expected_token))
     }
 
+    /// Consume the next token, EOF is an error
+    fn next_token(&mut self) -> Result<Token> {
+        self.tokenizer
+            .next()
+            .unwrap_or_else(|| Err(anyhow!("Parsing: unexpected EOF")))
+    }
+
+    /// Consume the next token only if it matches the expected token
+    fn next_token_if(&mut self, expected_token: &Token) -> bool {
+        if let Some(Ok(peeked)) = self.tokenizer.peek()
+            && peeked == expected_token
+        {
+            self.tokenizer.next();
+            return true;
+        }
+        false
+    }
+
+    /// Keywords that are not reserved words are tokenized as identifiers
+    fn next_keyword_if(&mut self, keyword: &str) -> bool {
+        self.next_token_if(&Token::Ident(keyword.to_string()))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.next_keyword_if(keyword) {
+            Ok(())
+        } else {
+            Err(anyhow!("Parsing: expected {}", keyword.to_uppercase()))
+        }
+    }
+
+    // The statement ends at a semicolon or at the end of the query
+    fn expect_statement_end(&mut self) -> Result<()> {
+        match self.tokenizer.peek() {
+            None => Ok(()),
+            Some(Ok(Token::SemiColon)) => {
+                self.tokenizer.next();
+                Ok(())
+            }
+            Some(Ok(_)) => Err(anyhow!("Parsing: expected end of statement")),
+            // A token that cannot be read is returned as the error
+            Some(Err(_)) => self.next_token().map(|_| ()),
+        }
+    }
+
+    /// Name of a table, a column, an index or a collation
+    fn parse_name(&mut self) -> Result<String> {
+        match self.next_token()? {
+            Token::Ident(name) => Ok(name),
+            // Sqlite accepts string literals as names
+            Token::QIdent(name) => Ok(name.to_lowercase()),
+            token => Err(anyhow!("Parsing: expected a name got {}", token)),
+        }
+    }
+
     fn parse_select_statement(&mut self, token: Token) -> Result<Statement> {
         let select_clause = self.parse_select_clause(token)?;
-        let select_statement = SelectStatement::new(select_clause, "".to_string(), None);
+        let select_statement = SelectStatement::new(select_clause, None, None);
         let select_statement = self.try_parse_from(select_statement)?;
         let select_statement = self.try_parse_where(select_statement)?;
+        let select_statement = self.try_parse_group_by(select_statement)?;
+        let select_statement = self.try_parse_order_by(select_statement)?;
+        let select_statement = self.try_parse_limit(select_statement)?;
+        self.expect_statement_end()?;
 
         Ok(Statement::Select(select_statement))
     }
 
     fn parse_select_clause(&mut self, token: Token) -> Result<SelectClause> {
         let mut select = SelectClause::new(token);
+        // ALL is the default: duplicate rows are kept
+        if self.next_token_if(&Token::Distinct) {
+            select.distinct = true;
+        } else {
+            self.next_token_if(&Token::All);
+        }
 
         let select_items = self.parse_select_values()?;
         for item in select_items {
@@ -101,144 +181,905 @@ impl<'a> Parser<'a> {
     fn parse_select_values(&mut self) -> Result<Vec<SelectItem>> {
         let mut select_items = vec![];
         loop {
-            let Some(Ok(next)) = self.tokenizer.next() else {
-                return Err(anyhow!("Parsing Select Clause: expect token got EOF"));
-            };
-            match next {
-                Token::Ident(value) => {
-                    if self.is_function(&value) {
-                        let function = self.parse_function(value)?;
-                        select_items.push(function);
-                    } else {
-                        let identifier = Identifier {
-                            value: VType::Str(value),
-                        };
-                        select_items.push(SelectItem::Identifier(identifier));
-                    }
-                }
-                Token::Star => select_items.push(SelectItem::Star),
-                _ => {
-                    return Err(anyhow!(
-                        "Parsing Select Clause: expect column name got {}",
-                        next
-                    ));
-                }
+            if self.next_token_if(&Token::Star) {
+                select_items.push(SelectItem::Star);
+            } else if let Some(table) = self.try_parse_table_star()? {
+                select_items.push(SelectItem::TableStar(table));
+            } else {
+                let expr = self.parse_expr()?;
+                let alias = self.try_parse_alias()?;
+                select_items.push(SelectItem::Expr { expr, alias });
             }
 
-            if let Err(_) = self.expect_token_peek(Token::Coma) {
+            if !self.next_token_if(&Token::Coma) {
                 break;
             }
-            self.tokenizer.next();
         }
         Ok(select_items)
     }
 
-    fn is_function(&self, function_name: &str) -> bool {
-        match function_name {
-            "count" => true,
-            _ => false,
+    // `table.*` is tokenized as the identifier `table.` followed by `*`
+    fn try_parse_table_star(&mut self) -> Result<Option<String>> {
+        let table = match self.tokenizer.peek() {
+            Some(Ok(Token::Ident(name))) if name.ends_with('.') => {
+                name.trim_end_matches('.').to_string()
+            }
+            _ => return Ok(None),
+        };
+        self.tokenizer.next();
+        self.expect_token(Token::Star)?;
+        Ok(Some(table))
+    }
+
+    // `AS alias` or only `alias`, the alias can be a string
+    fn try_parse_alias(&mut self) -> Result<Option<String>> {
+        let has_as = self.next_token_if(&Token::As);
+        match self.tokenizer.peek() {
+            Some(Ok(Token::Ident(_) | Token::QIdent(_))) => match self.next_token()? {
+                Token::Ident(alias) | Token::QIdent(alias) => Ok(Some(alias)),
+                _ => unreachable!("The token is an identifier"),
+            },
+            _ if has_as => Err(anyhow!("Parsing: expected an alias after AS")),
+            _ => Ok(None),
         }
     }
 
-    fn parse_function(&mut self, function_name: String) -> Result<SelectItem> {
-        self.expect_token(Token::LParen)?;
-        let items = self.parse_select_values()?;
-        self.expect_token(Token::RParen)?;
-        Ok(SelectItem::Function(FuncCall::new(function_name, items)))
+    pub fn parse_expr(&mut self) -> Result<Expr> {
+        self.parse_expr_with_precedence(0)
     }
 
-    fn try_parse_from(&mut self, select_statement: SelectStatement) -> Result<SelectStatement> {
-        if self.is_statement_end() {
-            return Ok(select_statement);
+    // Precedence climbing: parse the operators binding tighter than `min_precedence`.
+    // An operator with the same precedence is parsed by the caller, so operators
+    // are left associative: `a - b - c` is `(a - b) - c`
+    fn parse_expr_with_precedence(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut left = self.parse_unary_expr()?;
+        while let Some(Ok(token)) = self.tokenizer.peek() {
+            if *token == Token::Collate {
+                if COLLATE_PRECEDENCE < min_precedence {
+                    break;
+                }
+                self.tokenizer.next();
+                let collation = self.parse_name()?;
+                left = Expr::Collate {
+                    expr: Box::new(left),
+                    collation,
+                };
+                continue;
+            }
+            // Predicates written with keywords, `NOT` after an operand negates
+            // the predicate that follows: `a NOT LIKE b`
+            if is_predicate_start(token) {
+                if EQUALITY_PRECEDENCE < min_precedence {
+                    break;
+                }
+                left = self.parse_predicate(left)?;
+                continue;
+            }
+            let Some(operator) = BinaryOperator::from_token(token) else {
+                break;
+            };
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.tokenizer.next();
+            let operator = if operator == BinaryOperator::Is && self.next_token_if(&Token::Not) {
+                BinaryOperator::IsNot
+            } else {
+                operator
+            };
+            let right = self.parse_expr_with_precedence(operator.precedence() + 1)?;
+            left = Expr::binary(left, operator, right);
         }
+        Ok(left)
+    }
 
-        let next = self
-            .tokenizer
-            .next()
-            .expect("We know from the last if statement that there is next token")?;
+    // Parse the predicate following its left operand:
+    // `[NOT] LIKE`, `[NOT] IN`, `[NOT] BETWEEN`, `ISNULL`, `NOTNULL` and `NOT NULL`
+    fn parse_predicate(&mut self, expr: Expr) -> Result<Expr> {
+        let negated = self.next_token_if(&Token::Not);
+        let token = self.next_token()?;
+        if let Some(operator) = LikeOperator::from_token(&token) {
+            return self.parse_like(expr, operator, negated);
+        }
+        let expr = Box::new(expr);
+        let predicate = match token {
+            Token::In => {
+                self.expect_token(Token::LParen)?;
+                let mut list = vec![];
+                if !self.next_token_if(&Token::RParen) {
+                    loop {
+                        list.push(self.parse_expr()?);
+                        if !self.next_token_if(&Token::Coma) {
+                            break;
+                        }
+                    }
+                    self.expect_token(Token::RParen)?;
+                }
+                Expr::InList {
+                    expr,
+                    list,
+                    negated,
+                }
+            }
+            Token::Between => {
+                // The AND of BETWEEN is not a logical operator
+                let low = self.parse_expr_with_precedence(EQUALITY_PRECEDENCE + 1)?;
+                self.expect_token(Token::And)?;
+                let high = self.parse_expr_with_precedence(EQUALITY_PRECEDENCE + 1)?;
+                Expr::Between {
+                    expr,
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                }
+            }
+            Token::Null if negated => {
+                Expr::binary(*expr, BinaryOperator::IsNot, Expr::Literal(RType::Null))
+            }
+            Token::IsNull if !negated => {
+                Expr::binary(*expr, BinaryOperator::Is, Expr::Literal(RType::Null))
+            }
+            Token::NotNull if !negated => {
+                Expr::binary(*expr, BinaryOperator::IsNot, Expr::Literal(RType::Null))
+            }
+            token => {
+                return Err(anyhow!(
+                    "Parsing: expected LIKE, GLOB, IN, BETWEEN or NULL after NOT got {}",
+                    token
+                ));
+            }
+        };
+        Ok(predicate)
+    }
+
+    fn parse_like(&mut self, expr: Expr, operator: LikeOperator, negated: bool) -> Result<Expr> {
+        let pattern = self.parse_expr_with_precedence(EQUALITY_PRECEDENCE + 1)?;
+        let escape = if operator != LikeOperator::Glob && self.next_keyword_if("escape") {
+            let escape = self.parse_expr_with_precedence(EQUALITY_PRECEDENCE + 1)?;
+            Some(Box::new(escape))
+        } else {
+            None
+        };
+        Ok(Expr::Like {
+            expr: Box::new(expr),
+            operator,
+            pattern: Box::new(pattern),
+            escape,
+            negated,
+        })
+    }
+
+    fn parse_unary_expr(&mut self) -> Result<Expr> {
+        let operator = match self.tokenizer.peek() {
+            Some(Ok(token)) => UnaryOperator::from_token(token),
+            _ => None,
+        };
+        let Some(operator) = operator else {
+            return self.parse_primary_expr();
+        };
+        self.tokenizer.next();
+        let expr = self.parse_expr_with_precedence(operator.precedence())?;
+        // Negative numbers are literals
+        let expr = match (operator, expr) {
+            (UnaryOperator::Minus, Expr::Literal(RType::Num(value))) if value != i64::MIN => {
+                Expr::Literal(RType::Num(-value))
+            }
+            // 9223372036854775808 is only an integer when negated
+            (UnaryOperator::Minus, Expr::Literal(RType::Real(value)))
+                if value == -(i64::MIN as f64) =>
+            {
+                Expr::Literal(RType::Num(i64::MIN))
+            }
+            (UnaryOperator::Minus, Expr::Literal(RType::Real(value))) => {
+                Expr::Literal(RType::Real(-value))
+            }
+            (operator, expr) => Expr::Unary {
+                operator,
+                expr: Box::new(expr),
+            },
+        };
+        Ok(expr)
+    }
+
+    fn parse_primary_expr(&mut self) -> Result<Expr> {
+        let expr = match self.next_token()? {
+            Token::Null => Expr::Literal(RType::Null),
+            token @ (Token::Num(_) | Token::Real(_) | Token::QIdent(_) | Token::Blob(_)) => {
+                Expr::Literal(token.into_rtype())
+            }
+            Token::Ident(name) => {
+                if self.next_token_if(&Token::LParen) {
+                    Expr::Function(self.parse_function(name)?)
+                } else {
+                    Expr::Column(name)
+                }
+            }
+            Token::LParen => {
+                let expr = self.parse_expr()?;
+                self.expect_token(Token::RParen)?;
+                expr
+            }
+            token => return Err(anyhow!("Parsing: expected an expression got {}", token)),
+        };
+        Ok(expr)
+    }
+
+    // Parse the arguments of a function call, after the opening parenthesis
+    fn parse_function(&mut self, function_name: String) -> Result<FuncCall> {
+        let distinct = self.next_token_if(&Token::Distinct);
+        let args = if !distinct && self.next_token_if(&Token::Star) {
+            FuncArgs::Star
+        } else if let Some(Ok(Token::RParen)) = self.tokenizer.peek() {
+            FuncArgs::List(vec![])
+        } else {
+            let mut args = vec![self.parse_expr()?];
+            while self.next_token_if(&Token::Coma) {
+                args.push(self.parse_expr()?);
+            }
+            FuncArgs::List(args)
+        };
+        self.expect_token(Token::RParen)?;
+        let mut func = FuncCall::new(function_name, args);
+        func.distinct = distinct;
+        Ok(func)
+    }
 
-        if let Token::From = next {
+    fn try_parse_from(&mut self, select_statement: SelectStatement) -> Result<SelectStatement> {
+        if self.next_token_if(&Token::From) {
             self.parse_from(select_statement)
         } else {
-            return Err(anyhow!("Parsing: expected From got {}", next));
+            Ok(select_statement)
         }
     }
 
     fn parse_from(&mut self, mut select_statement: SelectStatement) -> Result<SelectStatement> {
-        let Some(Ok(next)) = self.tokenizer.next() else {
+        let mut from_clause = FromClause::new(self.parse_table_ref()?);
+        while let Some(join) = self.try_parse_join()? {
+            from_clause.joins.push(join);
+        }
+        select_statement.add_from(from_clause);
+        Ok(select_statement)
+    }
+
+    fn parse_table_ref(&mut self) -> Result<TableRef> {
+        let Some(next) = self.tokenizer.next() else {
             return Err(anyhow!("Parsing: expected table in FROM statement got EOF",));
         };
-        if let Token::Ident(value) = next {
-            select_statement.add_from(value);
-        } else {
+        let next = next?;
+        let Token::Ident(name) = next else {
             return Err(anyhow!("Parsing:: expect table identifier got: {}", next));
+        };
+        let alias = self.try_parse_alias()?;
+        Ok(TableRef { name, alias })
+    }
+
+    // `, table` or `[NATURAL] [LEFT [OUTER] | INNER | CROSS] JOIN table [ON expr | USING (column, ...)]`
+    fn try_parse_join(&mut self) -> Result<Option<Join>> {
+        if self.next_token_if(&Token::Coma) {
+            return Ok(Some(Join {
+                operator: JoinOperator::Comma,
+                natural: false,
+                table: self.parse_table_ref()?,
+                constraint: None,
+            }));
+        }
+        let natural = self.next_token_if(&Token::Natural);
+        let operator = if self.next_token_if(&Token::Left) {
+            self.next_token_if(&Token::Outer);
+            Some(JoinOperator::Left)
+        } else if self.next_token_if(&Token::Cross) {
+            Some(JoinOperator::Cross)
+        } else if self.next_token_if(&Token::Inner) {
+            Some(JoinOperator::Inner)
+        } else {
+            None
+        };
+        if !natural && operator.is_none() && !matches!(self.tokenizer.peek(), Some(Ok(Token::Join)))
+        {
+            return Ok(None);
+        }
+        self.expect_token(Token::Join)?;
+        let operator = operator.unwrap_or(JoinOperator::Inner);
+        let table = self.parse_table_ref()?;
+        let constraint = if self.next_token_if(&Token::On) {
+            Some(JoinConstraint::On(self.parse_expr()?))
+        } else if self.next_token_if(&Token::Using) {
+            Some(JoinConstraint::Using(self.parse_column_names()?))
+        } else {
+            None
+        };
+        if natural && constraint.is_some() {
+            return Err(anyhow!(
+                "Parsing: a NATURAL join may not have an ON or USING clause"
+            ));
+        }
+        Ok(Some(Join {
+            operator,
+            natural,
+            table,
+            constraint,
+        }))
+    }
+
+    fn try_parse_where(
+        &mut self,
+        mut select_statement: SelectStatement,
+    ) -> Result<SelectStatement> {
+        if self.next_token_if(&Token::Where) {
+            select_statement.where_clause = Some(Where::new(self.parse_expr()?));
         }
         Ok(select_statement)
     }
 
-    fn is_statement_end(&mut self) -> bool {
-        if let Some(Ok(peek)) = self.tokenizer.peek() {
-            match peek {
-                Token::SemiColon => {
-                    self.tokenizer.next();
-                    true
+    fn try_parse_group_by(
+        &mut self,
+        mut select_statement: SelectStatement,
+    ) -> Result<SelectStatement> {
+        if self.next_token_if(&Token::Group) {
+            self.expect_token(Token::By)?;
+            loop {
+                select_statement.group_by.push(self.parse_expr()?);
+                if !self.next_token_if(&Token::Coma) {
+                    break;
                 }
-                _ => false,
             }
-        } else {
-            self.tokenizer.next();
-            true
         }
+        if self.next_token_if(&Token::Having) {
+            select_statement.having = Some(self.parse_expr()?);
+        }
+        Ok(select_statement)
     }
 
-    fn try_parse_where(&mut self, select_statement: SelectStatement) -> Result<SelectStatement> {
-        if self.is_statement_end() {
+    fn try_parse_order_by(
+        &mut self,
+        mut select_statement: SelectStatement,
+    ) -> Result<SelectStatement> {
+        if !self.next_token_if(&Token::Order) {
             return Ok(select_statement);
         }
+        self.expect_token(Token::By)?;
+        loop {
+            select_statement.order_by.push(self.parse_ordering_term()?);
+            if !self.next_token_if(&Token::Coma) {
+                break;
+            }
+        }
+        Ok(select_statement)
+    }
 
-        let next = self
-            .tokenizer
-            .next()
-            .expect("We know from the last if statement that there is next token")?;
+    fn try_parse_limit(
+        &mut self,
+        mut select_statement: SelectStatement,
+    ) -> Result<SelectStatement> {
+        if !self.next_token_if(&Token::Limit) {
+            return Ok(select_statement);
+        }
+        let first = self.parse_expr()?;
+        let limit = if self.next_token_if(&Token::Offset) {
+            Limit {
+                limit: first,
+                offset: Some(self.parse_expr()?),
+            }
+        } else if self.next_token_if(&Token::Coma) {
+            // The offset comes first with a comma
+            Limit {
+                limit: self.parse_expr()?,
+                offset: Some(first),
+            }
+        } else {
+            Limit {
+                limit: first,
+                offset: None,
+            }
+        };
+        select_statement.limit = Some(limit);
+        Ok(select_statement)
+    }
 
-        if let Token::Where = next {
-            self.parse_where(select_statement)
+    // expr [COLLATE name] [ASC | DESC] [NULLS FIRST | NULLS LAST]
+    fn parse_ordering_term(&mut self) -> Result<OrderingTerm> {
+        // The COLLATE of the whole term is the collation of the ordering
+        let (expr, collation) = match self.parse_expr()? {
+            Expr::Collate { expr, collation } => (*expr, Some(collation)),
+            expr => (expr, None),
+        };
+        let order = self.parse_sort_order();
+        let nulls = if self.next_keyword_if("nulls") {
+            if self.next_keyword_if("first") {
+                Some(NullsOrder::First)
+            } else {
+                self.expect_keyword("last")?;
+                Some(NullsOrder::Last)
+            }
         } else {
-            return Err(anyhow!("Parsing: expected From got {}", next));
+            None
+        };
+        Ok(OrderingTerm {
+            expr,
+            collation,
+            order,
+            nulls,
+        })
+    }
+
+    fn parse_create_statement(&mut self) -> Result<Statement> {
+        match self.next_token()? {
+            Token::Table => Ok(Statement::CreateTable(self.parse_create_table()?)),
+            Token::Index => Ok(Statement::CreateIndex(self.parse_create_index(false)?)),
+            Token::Unique => {
+                self.expect_token(Token::Index)?;
+                Ok(Statement::CreateIndex(self.parse_create_index(true)?))
+            }
+            Token::Ident(keyword) if keyword == "view" => {
+                Ok(Statement::CreateView(self.parse_create_view()?))
+            }
+            token => Err(anyhow!("Parsing: CREATE {} is not supported", token)),
+        }
+    }
+
+    fn parse_if_not_exists(&mut self) -> Result<bool> {
+        if !self.next_keyword_if("if") {
+            return Ok(false);
+        }
+        self.expect_token(Token::Not)?;
+        self.expect_keyword("exists")?;
+        Ok(true)
+    }
+
+    fn parse_create_table(&mut self) -> Result<CreateTableStatement> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_name()?;
+        self.expect_token(Token::LParen)?;
+        let mut columns = vec![];
+        let mut constraints = vec![];
+        // Table constraints always come after the column definitions
+        loop {
+            if constraints.is_empty() && !self.is_table_constraint_start() {
+                columns.push(self.parse_column_def()?);
+            } else {
+                constraints.push(self.parse_table_constraint()?);
+            }
+            if !self.next_token_if(&Token::Coma) {
+                break;
+            }
+        }
+        self.expect_token(Token::RParen)?;
+
+        let mut statement = CreateTableStatement {
+            name,
+            if_not_exists,
+            columns,
+            constraints,
+            without_rowid: false,
+            strict: false,
+        };
+        // Table options are separated by comas
+        loop {
+            if self.next_keyword_if("without") {
+                self.expect_keyword("rowid")?;
+                statement.without_rowid = true;
+            } else if self.next_keyword_if("strict") {
+                statement.strict = true;
+            } else {
+                break;
+            }
+            if !self.next_token_if(&Token::Coma) {
+                break;
+            }
+        }
+        self.expect_statement_end()?;
+        Ok(statement)
+    }
+
+    fn is_table_constraint_start(&mut self) -> bool {
+        matches!(
+            self.tokenizer.peek(),
+            Some(Ok(Token::Constraint
+                | Token::Primary
+                | Token::Unique
+                | Token::Check
+                | Token::Foreign))
+        )
+    }
+
+    fn parse_column_def(&mut self) -> Result<ColumnDef> {
+        let name = self.parse_name()?;
+        let type_name = self.parse_type_name()?;
+        let mut constraints = vec![];
+        while self.parse_column_constraint(&mut constraints)? {}
+        Ok(ColumnDef::new(name, type_name, constraints))
+    }
+
+    // A type name is one or more words, followed by one or two sizes: DECIMAL(10, 2)
+    fn parse_type_name(&mut self) -> Result<Option<String>> {
+        let mut words = vec![];
+        while let Some(Ok(Token::Ident(word))) = self.tokenizer.peek() {
+            if word == "generated" {
+                break;
+            }
+            words.push(word.to_string());
+            self.tokenizer.next();
+        }
+        if words.is_empty() {
+            return Ok(None);
+        }
+
+        let mut type_name = words.join(" ");
+        if self.next_token_if(&Token::LParen) {
+            let mut sizes = vec![self.parse_signed_number()?.to_string()];
+            if self.next_token_if(&Token::Coma) {
+                sizes.push(self.parse_signed_number()?.to_string());
+            }
+            self.expect_token(Token::RParen)?;
+            type_name = format!("{}({})", type_name, sizes.join(","));
+        }
+        Ok(Some(type_name))
+    }
+
+    fn parse_signed_number(&mut self) -> Result<RType> {
+        let negative = self.next_token_if(&Token::Minus);
+        if !negative {
+            self.next_token_if(&Token::Plus);
+        }
+        match (self.next_token()?, negative) {
+            (Token::Num(value), false) => Ok(RType::Num(value)),
+            (Token::Num(value), true) => Ok(RType::Num(-value)),
+            (Token::Real(value), false) => Ok(RType::Real(value)),
+            (Token::Real(value), true) => Ok(RType::Real(-value)),
+            (token, _) => Err(anyhow!("Parsing: expected a number got {}", token)),
+        }
+    }
+
+    /// Parse one column constraint into `constraints`.
+    /// Returns false when the next token does not start a constraint.
+    fn parse_column_constraint(&mut self, constraints: &mut Vec<ColumnConstraint>) -> Result<bool> {
+        let constraint = match self.tokenizer.peek() {
+            Some(Ok(Token::Constraint)) => {
+                self.tokenizer.next();
+                self.parse_name()?;
+                return Ok(true);
+            }
+            Some(Ok(Token::Primary)) => {
+                self.tokenizer.next();
+                self.expect_keyword("key")?;
+                let order = self.parse_sort_order();
+                self.parse_conflict_clause()?;
+                let autoincrement = self.next_keyword_if("autoincrement");
+                ColumnConstraint::PrimaryKey {
+                    order,
+                    autoincrement,
+                }
+            }
+            Some(Ok(Token::Not)) => {
+                self.tokenizer.next();
+                // NOT DEFERRABLE of a REFERENCES clause
+                if self.next_keyword_if("deferrable") {
+                    self.parse_deferrable()?;
+                    return Ok(true);
+                }
+                self.expect_token(Token::Null)?;
+                self.parse_conflict_clause()?;
+                ColumnConstraint::NotNull
+            }
+            Some(Ok(Token::Null)) => {
+                self.tokenizer.next();
+                self.parse_conflict_clause()?;
+                return Ok(true);
+            }
+            Some(Ok(Token::Unique)) => {
+                self.tokenizer.next();
+                self.parse_conflict_clause()?;
+                ColumnConstraint::Unique
+            }
+            Some(Ok(Token::Check)) => {
+                self.tokenizer.next();
+                ColumnConstraint::Check(self.parse_parenthesized_expr()?)
+            }
+            Some(Ok(Token::Default)) => {
+                self.tokenizer.next();
+                ColumnConstraint::Default(self.parse_default_value()?)
+            }
+            Some(Ok(Token::Collate)) => {
+                self.tokenizer.next();
+                ColumnConstraint::Collate(self.parse_name()?)
+            }
+            Some(Ok(Token::References)) => {
+                self.tokenizer.next();
+                ColumnConstraint::References(self.parse_foreign_key_clause()?)
+            }
+            // [GENERATED ALWAYS] AS (expr) [STORED | VIRTUAL]
+            Some(Ok(Token::As)) | Some(Ok(Token::Ident(_))) => {
+                if self.next_keyword_if("generated") {
+                    self.expect_keyword("always")?;
+                    self.expect_token(Token::As)?;
+                } else if !self.next_token_if(&Token::As) {
+                    return Ok(false);
+                }
+                let expr = self.parse_parenthesized_expr()?;
+                let stored = self.next_keyword_if("stored");
+                if !stored {
+                    self.next_keyword_if("virtual");
+                }
+                ColumnConstraint::Generated { expr, stored }
+            }
+            _ => return Ok(false),
+        };
+        constraints.push(constraint);
+        Ok(true)
+    }
+
+    fn parse_default_value(&mut self) -> Result<DefaultValue> {
+        let value = match self.tokenizer.peek() {
+            Some(Ok(Token::LParen)) => {
+                return Ok(DefaultValue::Expr(self.parse_parenthesized_expr()?));
+            }
+            Some(Ok(Token::Plus | Token::Minus | Token::Num(_) | Token::Real(_))) => {
+                self.parse_signed_number()?
+            }
+            _ => match self.next_token()? {
+                Token::Null => RType::Null,
+                Token::QIdent(value) => RType::Str(value),
+                Token::Blob(value) => RType::Blob(value),
+                Token::Ident(keyword) => match keyword.as_str() {
+                    "true" => RType::Num(1),
+                    "false" => RType::Num(0),
+                    // CURRENT_TIME, CURRENT_DATE and CURRENT_TIMESTAMP
+                    _ => return Ok(DefaultValue::Expr(keyword)),
+                },
+                token => return Err(anyhow!("Parsing: invalid default value {}", token)),
+            },
+        };
+        Ok(DefaultValue::Literal(value))
+    }
+
+    fn parse_table_constraint(&mut self) -> Result<TableConstraint> {
+        if self.next_token_if(&Token::Constraint) {
+            self.parse_name()?;
         }
+        let constraint = match self.next_token()? {
+            Token::Primary => {
+                self.expect_keyword("key")?;
+                let columns = self.parse_indexed_columns()?;
+                self.parse_conflict_clause()?;
+                TableConstraint::PrimaryKey(columns)
+            }
+            Token::Unique => {
+                let columns = self.parse_indexed_columns()?;
+                self.parse_conflict_clause()?;
+                TableConstraint::Unique(columns)
+            }
+            Token::Check => TableConstraint::Check(self.parse_parenthesized_expr()?),
+            Token::Foreign => {
+                self.expect_keyword("key")?;
+                let columns = self.parse_column_names()?;
+                self.expect_token(Token::References)?;
+                let references = self.parse_foreign_key_clause()?;
+                if self.next_token_if(&Token::Not) {
+                    self.expect_keyword("deferrable")?;
+                    self.parse_deferrable()?;
+                }
+                TableConstraint::ForeignKey {
+                    columns,
+                    references,
+                }
+            }
+            token => return Err(anyhow!("Parsing: expected table constraint got {}", token)),
+        };
+        Ok(constraint)
     }
 
-    fn parse_where(&mut self, mut select_statement: SelectStatement) -> Result<SelectStatement> {
-        let Some(Ok(left)) = self.tokenizer.next() else {
-            return Err(anyhow!("Parsing:: expect where identifier left"));
+    // Parse a REFERENCES clause, after the REFERENCES keyword
+    fn parse_foreign_key_clause(&mut self) -> Result<ForeignKey> {
+        let table = self.parse_name()?;
+        let columns = if let Some(Ok(Token::LParen)) = self.tokenizer.peek() {
+            self.parse_column_names()?
+        } else {
+            vec![]
         };
+        loop {
+            if self.next_token_if(&Token::On) {
+                // ON DELETE | UPDATE, then SET NULL, SET DEFAULT, CASCADE, RESTRICT or NO ACTION
+                self.next_token()?;
+                if let Token::Ident(action) = self.next_token()?
+                    && (action == "set" || action == "no")
+                {
+                    self.next_token()?;
+                }
+            } else if self.next_keyword_if("match") {
+                self.parse_name()?;
+            } else if self.next_keyword_if("deferrable") {
+                self.parse_deferrable()?;
+            } else {
+                break;
+            }
+        }
+        Ok(ForeignKey { table, columns })
+    }
+
+    // INITIALLY DEFERRED | IMMEDIATE, after DEFERRABLE
+    fn parse_deferrable(&mut self) -> Result<()> {
+        if self.next_keyword_if("initially") {
+            self.parse_name()?;
+        }
+        Ok(())
+    }
+
+    // ON CONFLICT ROLLBACK | ABORT | FAIL | IGNORE | REPLACE
+    fn parse_conflict_clause(&mut self) -> Result<()> {
+        if self.next_token_if(&Token::On) {
+            self.expect_keyword("conflict")?;
+            self.parse_name()?;
+        }
+        Ok(())
+    }
+
+    fn parse_sort_order(&mut self) -> Option<SortOrder> {
+        if self.next_keyword_if("asc") {
+            Some(SortOrder::Asc)
+        } else if self.next_keyword_if("desc") {
+            Some(SortOrder::Desc)
+        } else {
+            None
+        }
+    }
+
+    // (name, name, ...)
+    fn parse_column_names(&mut self) -> Result<Vec<String>> {
+        self.expect_token(Token::LParen)?;
+        let mut names = vec![self.parse_name()?];
+        while self.next_token_if(&Token::Coma) {
+            names.push(self.parse_name()?);
+        }
+        self.expect_token(Token::RParen)?;
+        Ok(names)
+    }
+
+    // (column [COLLATE name] [ASC | DESC], ...)
+    fn parse_indexed_columns(&mut self) -> Result<Vec<IndexedColumn>> {
+        self.expect_token(Token::LParen)?;
+        let mut columns = vec![];
+        loop {
+            let name = self.parse_raw_expr(|token| {
+                matches!(token, Token::Coma | Token::Collate)
+                    || matches!(token, Token::Ident(keyword) if keyword == "asc" || keyword == "desc")
+            })?;
+            let collation = if self.next_token_if(&Token::Collate) {
+                Some(self.parse_name()?)
+            } else {
+                None
+            };
+            let order = self.parse_sort_order();
+            columns.push(IndexedColumn {
+                name,
+                collation,
+                order,
+            });
+            if !self.next_token_if(&Token::Coma) {
+                break;
+            }
+        }
+        self.expect_token(Token::RParen)?;
+        Ok(columns)
+    }
+
+    fn parse_parenthesized_expr(&mut self) -> Result<String> {
+        self.expect_token(Token::LParen)?;
+        let expr = self.parse_raw_expr(|_| false)?;
+        self.expect_token(Token::RParen)?;
+        Ok(expr)
+    }
+
+    // Read an expression up to a token accepted by `is_end` or a closing parenthesis,
+    // outside of any nested parentheses.
+    // These expressions are not evaluated, they are kept as SQL text.
+    fn parse_raw_expr(&mut self, is_end: fn(&Token) -> bool) -> Result<String> {
+        let mut tokens = vec![];
+        let mut depth = 0;
+        loop {
+            match self.tokenizer.peek() {
+                None => break,
+                Some(Ok(token)) if depth == 0 && (*token == Token::RParen || is_end(token)) => {
+                    break;
+                }
+                _ => {}
+            }
+            let token = self.next_token()?;
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+        if tokens.is_empty() {
+            return Err(anyhow!("Parsing: expected an expression"));
+        }
+        Ok(tokens_to_sql(&tokens))
+    }
 
-        let Some(Ok(operator)) = self.tokenizer.next() else {
-            return Err(anyhow!("Parsing:: expect operator token"));
+    fn parse_create_view(&mut self) -> Result<CreateViewStatement> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_name()?;
+        let columns = if let Some(Ok(Token::LParen)) = self.tokenizer.peek() {
+            self.parse_column_names()?
+        } else {
+            vec![]
+        };
+        self.expect_token(Token::As)?;
+        let token = self.expect_token(Token::Select)?;
+        let Statement::Select(select) = self.parse_select_statement(token)? else {
+            unreachable!("parse_select_statement always returns a select statement");
         };
+        Ok(CreateViewStatement {
+            name,
+            if_not_exists,
+            columns,
+            select,
+        })
+    }
 
-        let Some(Ok(right)) = self.tokenizer.next() else {
-            return Err(anyhow!("Parsing:: expect where identifier right"));
+    fn parse_create_index(&mut self, unique: bool) -> Result<CreateIndexStatement> {
+        let if_not_exists = self.parse_if_not_exists()?;
+        let name = self.parse_name()?;
+        self.expect_token(Token::On)?;
+        let tablename = self.parse_name()?;
+        let columns = self.parse_indexed_columns()?;
+        let where_clause = if self.next_token_if(&Token::Where) {
+            Some(self.parse_raw_expr(|token| *token == Token::SemiColon)?)
+        } else {
+            None
         };
-        select_statement.where_clause = Some(Where::new(left, operator, right)?);
+        self.expect_statement_end()?;
+        Ok(CreateIndexStatement {
+            name,
+            unique,
+            if_not_exists,
+            tablename,
+            columns,
+            where_clause,
+        })
+    }
+}
 
-        Ok(select_statement)
+// Tokens that continue an expression as a predicate: NOT, IN, BETWEEN, ISNULL, NOTNULL, LIKE, GLOB
+fn is_predicate_start(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Not | Token::In | Token::Between | Token::IsNull | Token::NotNull
+    ) || LikeOperator::from_token(token).is_some()
+}
+
+// Join tokens back into SQL text: `lower(name) = 'a'`
+fn tokens_to_sql(tokens: &[Token]) -> String {
+    let mut sql = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let glued = i == 0
+            || matches!(token, Token::RParen | Token::Coma)
+            || matches!(tokens[i - 1], Token::LParen)
+            || (*token == Token::LParen && matches!(tokens[i - 1], Token::Ident(_)));
+        if !glued {
+            sql.push(' ');
+        }
+        sql.push_str(&token.to_string());
     }
+    sql
 }
 
 impl Iterator for Parser<'_> {
     type Item = Result<Statement>;
 
     // Parse the next query.
-    // We handle only one statement for now, the select statement.
+    // We handle the select statement and the create statements of the schema.
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(Ok(token)) = self.tokenizer.next() else {
-            return None;
+        let token = match self.tokenizer.next()? {
+            Ok(token) => token,
+            Err(err) => return Some(Err(err)),
         };
         let stmt = match token {
             Token::Select => self.parse_select_statement(token),
+            Token::Create => self.parse_create_statement(),
             Token::Command(cmd) => Ok(Statement::Command(cmd)),
-            _ => todo!("Do update and insert ..."),
+            token => Err(anyhow!("Parsing: {} statements are not supported", token)),
         };
 
         Some(stmt)
@@ -248,6 +1089,7 @@ impl Iterator for Parser<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::create_table::Affinity;
 
     #[test]
     fn it_should_parse_select_regular_cols_name() {
@@ -279,6 +1121,38 @@ mod tests {
         assert_eq!(query, result)
     }
 
+    #[test]
+    fn it_should_parse_collate() {
+        let query = "SELECT name COLLATE nocase FROM apples WHERE -size COLLATE rtrim = 'A' \
+                     GROUP BY (color || name) COLLATE nocase ORDER BY name COLLATE nocase DESC";
+        let parsed_query = Parser::new(query).next().unwrap().unwrap();
+        assert_eq!(parsed_query.to_string(), query);
+    }
+
+    #[test]
+    fn it_should_parse_joins() {
+        let queries = [
+            (
+                "SELECT a.name, b.* FROM apples a JOIN baskets AS b ON b.apple_id = a.id",
+                "SELECT a.name, b.* FROM apples AS a JOIN baskets AS b ON b.apple_id = a.id",
+            ),
+            (
+                "SELECT * FROM apples LEFT OUTER JOIN baskets USING (id, color), farms",
+                "SELECT * FROM apples LEFT JOIN baskets USING (id, color), farms",
+            ),
+            (
+                "SELECT * FROM apples NATURAL INNER JOIN baskets CROSS JOIN farms WHERE id = 1",
+                "SELECT * FROM apples NATURAL JOIN baskets CROSS JOIN farms WHERE id = 1",
+            ),
+        ];
+        for (query, expected) in queries {
+            let parsed_query = Parser::new(query).next().unwrap().unwrap();
+            assert_eq!(parsed_query.to_string(), expected);
+        }
+        let natural_on = "SELECT * FROM apples NATURAL JOIN baskets ON id = 1";
+        assert!(Parser::new(natural_on).next().unwrap().is_err());
+    }
+
     #[test]
     fn it_should_parse_where() {
         let query = "SELECT COUNT(*) FROM apples WHERE name = 'green'";
@@ -288,4 +1162,299 @@ mod tests {
         let result = format!("{}", parsed_query);
         assert_eq!(query, result)
     }
+
+    #[test]
+    fn it_should_parse_expressions_with_precedence() {
+        let query = "SELECT a + b * 2, (a + b) * 2, a - (b - c), -(a || 'x') FROM apples \
+                     WHERE (a - 1) * 2 > -3 = 1";
+        let mut parser = Parser::new(
+            "SELECT a + (b * 2), (a + b) * 2, a - (b - c), -(a || 'x') FROM apples \
+             WHERE ((a - 1) * 2 > -3) = 1",
+        );
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_logical_operators_with_precedence() {
+        let query = "SELECT name FROM apples \
+                     WHERE NOT color = 'red' AND size > 3 OR (size < 1 OR NOT NOT size = 2) AND a";
+        let mut parser = Parser::new(
+            "SELECT name FROM apples \
+             WHERE ((NOT (color = 'red')) AND size > 3) OR ((size < 1 OR NOT NOT size = 2) AND a)",
+        );
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_is_not() {
+        let query = "SELECT name FROM apples WHERE color IS NOT NULL = (size IS 3)";
+        let mut parser =
+            Parser::new("SELECT name FROM apples WHERE (color IS NOT NULL) == (size IS 3)");
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_pattern_operators() {
+        let query = "SELECT name FROM apples WHERE name NOT LIKE 'a!%%' ESCAPE '!' \
+                     AND color GLOB '[rg]*' OR name ILIKE 'x' || '%' = 0";
+        let mut parser = Parser::new(
+            "SELECT name FROM apples WHERE (name not like 'a!%%' escape '!') \
+             AND (color GLOB '[rg]*') OR ((name ILIKE ('x' || '%')) = 0)",
+        );
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_in_between_and_null_predicates() {
+        let query = "SELECT name FROM apples WHERE color NOT IN ('red', 'green') \
+                     AND size BETWEEN 1 + 1 AND 3 AND size NOT BETWEEN 0 AND 1 = 0 \
+                     AND id IN () AND name IS NULL AND name IS NOT NULL AND name IS NOT NULL";
+        let mut parser = Parser::new(
+            "SELECT name FROM apples WHERE color NOT IN ('red','green') \
+             AND size BETWEEN 1 + 1 AND 3 AND (size NOT BETWEEN 0 AND 1) = 0 \
+             AND id IN () AND name ISNULL AND name NOTNULL AND name NOT NULL",
+        );
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_order_by() {
+        let query = "SELECT name, size FROM apples WHERE size > 1 \
+                     ORDER BY 2 DESC, name COLLATE nocase ASC NULLS LAST, size * -1 NULLS FIRST";
+        let mut parser = Parser::new(query);
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_group_by() {
+        let query = "SELECT color, COUNT(*) FROM apples WHERE size > 1 \
+                     GROUP BY color, size % 2 HAVING COUNT(DISTINCT size) > 2 ORDER BY 2";
+        let mut parser = Parser::new(query);
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_select_aliases() {
+        let query = "SELECT name || ' (' || color || ')' AS label, price * 1.2 AS taxed \
+                     FROM apples ORDER BY taxed";
+        let mut parser = Parser::new(query);
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!(query, format!("{}", parsed_query));
+
+        // AS is optional and an alias can be a string
+        let mut parser = Parser::new("SELECT name n, color 'Tint' FROM apples");
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!(
+            "SELECT name AS n, color AS Tint FROM apples",
+            format!("{}", parsed_query)
+        );
+    }
+
+    #[test]
+    fn it_should_parse_select_distinct() {
+        let query = "SELECT DISTINCT color, size FROM apples";
+        let mut parser = Parser::new(query);
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!(query, format!("{}", parsed_query));
+
+        let mut parser = Parser::new("SELECT ALL color FROM apples");
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!("SELECT color FROM apples", format!("{}", parsed_query));
+    }
+
+    #[test]
+    fn it_should_parse_limit() {
+        let query = "SELECT name FROM apples ORDER BY name LIMIT 10 OFFSET 2 + 3";
+        let mut parser = Parser::new(query);
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!(query, format!("{}", parsed_query));
+
+        // The offset comes first when the clause uses a comma
+        let mut parser = Parser::new("SELECT name FROM apples LIMIT 5, 10");
+        let parsed_query = parser.next().unwrap().unwrap();
+        assert_eq!(
+            "SELECT name FROM apples LIMIT 10 OFFSET 5",
+            format!("{}", parsed_query)
+        );
+    }
+
+    #[test]
+    fn it_should_parse_create_table() {
+        let query = "CREATE TABLE IF NOT EXISTS items (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                     price DECIMAL(10,2) NOT NULL CHECK (price > 0), \
+                     label TEXT COLLATE nocase DEFAULT 'it''s', weight REAL DEFAULT -1, \
+                     total AS (price * weight) STORED, \
+                     order_id INTEGER REFERENCES orders (id), \
+                     UNIQUE (label, order_id DESC), CHECK (weight >= 0), \
+                     FOREIGN KEY (order_id) REFERENCES orders (id)) WITHOUT ROWID, STRICT";
+        let mut parser = Parser::new(query);
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_create_table_column_definitions() {
+        let mut parser = Parser::new(
+            "CREATE TABLE \"Order Items\" ( -- items of an order
+                [line no] int,
+                price DECIMAL(10, 2) CONSTRAINT positive CHECK(price > 0),
+                /* no type */ note,
+                CONSTRAINT pk PRIMARY KEY (\"line no\")
+            )",
+        );
+
+        let Some(Ok(Statement::CreateTable(create_table))) = parser.next() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+        assert_eq!(create_table.name, "order items");
+        let columns = create_table
+            .columns
+            .iter()
+            .map(|column| {
+                (
+                    column.name.as_str(),
+                    column.type_name.as_deref(),
+                    column.affinity,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                ("line no", Some("int"), Affinity::Integer),
+                ("price", Some("decimal(10,2)"), Affinity::Numeric),
+                ("note", None, Affinity::Blob),
+            ]
+        );
+        assert_eq!(create_table.constraints.len(), 1);
+    }
+
+    #[test]
+    fn it_should_parse_generated_columns() {
+        let mut parser = Parser::new(
+            "CREATE TABLE t (a TEXT, b INT GENERATED ALWAYS AS (length(a)) VIRTUAL, \
+             c GENERATED ALWAYS AS (a || 'x') STORED, d AS (1))",
+        );
+
+        let Some(Ok(Statement::CreateTable(create_table))) = parser.next() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+        let generated = create_table
+            .columns
+            .iter()
+            .map(|column| match column.constraints.as_slice() {
+                [ColumnConstraint::Generated { expr, stored }] => Some((expr.as_str(), *stored)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            generated,
+            vec![
+                None,
+                Some(("length(a)", false)),
+                Some(("a || 'x'", true)),
+                Some(("1", false)),
+            ]
+        );
+        assert_eq!(create_table.columns[2].type_name, None);
+    }
+
+    #[test]
+    fn it_should_parse_blob_and_hexadecimal_default_values() {
+        let mut parser = Parser::new(
+            "CREATE TABLE t (a BLOB DEFAULT x'00ff', b INT DEFAULT 0x10, \
+             c INT DEFAULT -0X1F, d BLOB DEFAULT X'')",
+        );
+
+        let Some(Ok(Statement::CreateTable(create_table))) = parser.next() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+        let defaults = create_table
+            .columns
+            .iter()
+            .map(|column| match column.get_default() {
+                Some(DefaultValue::Literal(value)) => value.clone(),
+                _ => panic!("Expected a literal default value"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            defaults,
+            vec![
+                RType::Blob(vec![0x00, 0xff]),
+                RType::Num(16),
+                RType::Num(-31),
+                RType::Blob(vec![]),
+            ]
+        );
+        assert!(
+            Parser::new("CREATE TABLE t (a BLOB DEFAULT x'0')")
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn it_should_parse_create_view() {
+        let query = "CREATE VIEW IF NOT EXISTS red_apples (apple, tint) AS SELECT name, color \
+                     FROM apples WHERE color = 'red'";
+        let mut parser = Parser::new(query);
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_parse_create_index() {
+        let query = "CREATE UNIQUE INDEX idx_label ON items (lower(label) COLLATE nocase DESC, id) \
+                     WHERE weight > 2";
+        let mut parser = Parser::new(query);
+
+        let parsed_query = parser.next().unwrap().unwrap();
+        let result = format!("{}", parsed_query);
+        assert_eq!(query, result)
+    }
+
+    #[test]
+    fn it_should_return_the_errors_of_the_tokenizer() {
+        let queries = [
+            (
+                "SELECT id FROM authors WHERE id < 3 'oops",
+                "Unterminated quoted value",
+            ),
+            (
+                "SELECT id FROM authors WHERE id < 3 x'zz'",
+                "Unrecognized token",
+            ),
+            ("SELECT x'0g'", "Unrecognized token"),
+        ];
+        for (query, expected) in queries {
+            let err = Parser::new(query).next().unwrap().unwrap_err();
+            assert!(err.to_string().starts_with(expected), "{}: {}", query, err);
+        }
+    }
 }
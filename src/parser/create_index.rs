@@ -0,0 +1,41 @@
+//! CREATE INDEX statement, as stored in the sqlite_schema table.
+//!
+//! See [CREATE INDEX](https://www.sqlite.org/lang_createindex.html).
+use crate::parser::create_table::IndexedColumn;
+use itertools::Itertools;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct CreateIndexStatement {
+    pub name: String,
+    pub unique: bool,
+    pub if_not_exists: bool,
+    pub tablename: String,
+    pub columns: Vec<IndexedColumn>,
+    /// WHERE clause SQL text of a partial index
+    pub where_clause: Option<String>,
+}
+
+impl fmt::Display for CreateIndexStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CREATE ")?;
+        if self.unique {
+            write!(f, "UNIQUE ")?;
+        }
+        write!(f, "INDEX ")?;
+        if self.if_not_exists {
+            write!(f, "IF NOT EXISTS ")?;
+        }
+        write!(
+            f,
+            "{} ON {} ({})",
+            self.name,
+            self.tablename,
+            self.columns.iter().join(", ")
+        )?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        Ok(())
+    }
+}
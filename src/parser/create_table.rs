@@ -0,0 +1,491 @@
+//! CREATE TABLE statement, as stored in the sqlite_schema table.
+//!
+//! See [CREATE TABLE](https://www.sqlite.org/lang_createtable.html).
+//! Constraint names and conflict clauses are parsed but not kept.
+//! CHECK, DEFAULT and GENERATED expressions are kept as SQL text.
+use crate::executor::db_response::RType;
+use crate::parser::token::Token;
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct CreateTableStatement {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<ColumnDef>,
+    pub constraints: Vec<TableConstraint>,
+    pub without_rowid: bool,
+    pub strict: bool,
+}
+
+impl CreateTableStatement {
+    pub fn get_column(&self, name: &str) -> Option<&ColumnDef> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Primary key columns, in the primary key order.
+    /// Columns without explicit collation use the collation of their definition.
+    pub fn get_primary_key(&self) -> Vec<IndexedColumn> {
+        let mut primary_key: Vec<IndexedColumn> = vec![];
+        for column in self.columns.iter() {
+            for constraint in column.constraints.iter() {
+                if let ColumnConstraint::PrimaryKey { order, .. } = constraint {
+                    primary_key.push(IndexedColumn {
+                        name: column.name.to_string(),
+                        collation: column.get_collation().map(str::to_string),
+                        order: *order,
+                    });
+                }
+            }
+        }
+        for constraint in self.constraints.iter() {
+            if let TableConstraint::PrimaryKey(columns) = constraint {
+                for column in columns {
+                    let collation = column.collation.clone().or_else(|| {
+                        self.get_column(&column.name)
+                            .and_then(|def| def.get_collation().map(str::to_string))
+                    });
+                    primary_key.push(IndexedColumn {
+                        collation,
+                        ..column.clone()
+                    });
+                }
+            }
+        }
+        // A column listed twice is only stored once
+        let mut names = HashSet::new();
+        primary_key.retain(|column| names.insert(column.name.to_string()));
+        primary_key
+    }
+
+    /// Index of the column that is an alias for the rowid.
+    /// It is the only primary key column, declared with the exact type INTEGER.
+    /// `INTEGER PRIMARY KEY DESC` as a column constraint is not an alias, for backward
+    /// compatibility, while `PRIMARY KEY (x DESC)` as a table constraint is.
+    /// See [ROWID](https://www.sqlite.org/lang_createtable.html#rowid)
+    pub fn get_rowid_alias(&self) -> Option<usize> {
+        if self.without_rowid {
+            return None;
+        }
+        let mut primary_keys = self
+            .columns
+            .iter()
+            .filter_map(|column| {
+                column
+                    .constraints
+                    .iter()
+                    .find_map(|constraint| match constraint {
+                        ColumnConstraint::PrimaryKey { order, .. } => {
+                            Some((column.name.as_str(), *order))
+                        }
+                        _ => None,
+                    })
+            })
+            .chain(
+                self.constraints
+                    .iter()
+                    .filter_map(|constraint| match constraint {
+                        TableConstraint::PrimaryKey(columns) => Some(columns),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|column| (column.name.as_str(), None)),
+            );
+
+        let (name, order) = primary_keys.next()?;
+        if primary_keys.next().is_some() || order == Some(SortOrder::Desc) {
+            return None;
+        }
+        let index = self.columns.iter().position(|column| column.name == name)?;
+        self.columns[index]
+            .type_name
+            .as_deref()
+            .is_some_and(|type_name| type_name.eq_ignore_ascii_case("integer"))
+            .then_some(index)
+    }
+}
+
+impl fmt::Display for CreateTableStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CREATE TABLE ")?;
+        if self.if_not_exists {
+            write!(f, "IF NOT EXISTS ")?;
+        }
+        let definitions = self
+            .columns
+            .iter()
+            .map(|column| column.to_string())
+            .chain(
+                self.constraints
+                    .iter()
+                    .map(|constraint| constraint.to_string()),
+            )
+            .join(", ");
+        write!(f, "{} ({})", self.name, definitions)?;
+        let mut options = vec![];
+        if self.without_rowid {
+            options.push("WITHOUT ROWID");
+        }
+        if self.strict {
+            options.push("STRICT");
+        }
+        if !options.is_empty() {
+            write!(f, " {}", options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ColumnDef {
+    pub name: String,
+    /// Declared type as written in the DDL, `decimal(10,2)` for instance
+    pub type_name: Option<String>,
+    pub affinity: Affinity,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+impl ColumnDef {
+    pub fn new(
+        name: String,
+        type_name: Option<String>,
+        constraints: Vec<ColumnConstraint>,
+    ) -> Self {
+        Self {
+            affinity: Affinity::from_type_name(type_name.as_deref()),
+            name,
+            type_name,
+            constraints,
+        }
+    }
+
+    pub fn get_collation(&self) -> Option<&str> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                ColumnConstraint::Collate(collation) => Some(collation.as_str()),
+                _ => None,
+            })
+    }
+
+    pub fn get_default(&self) -> Option<&DefaultValue> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                ColumnConstraint::Default(value) => Some(value),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for ColumnDef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " {}", type_name.to_uppercase())?;
+        }
+        for constraint in self.constraints.iter() {
+            write!(f, " {}", constraint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Column type affinity, computed from the declared type.
+/// See 3.1. Determination Of Column Affinity in [Datatypes](https://www.sqlite.org/datatype3.html)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    pub fn from_type_name(type_name: Option<&str>) -> Self {
+        let Some(type_name) = type_name else {
+            return Affinity::Blob;
+        };
+        let type_name = type_name.to_uppercase();
+        if type_name.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|pattern| type_name.contains(pattern))
+        {
+            Affinity::Text
+        } else if type_name.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|pattern| type_name.contains(pattern))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Affinity::Integer | Affinity::Real | Affinity::Numeric)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortOrder::Asc => write!(f, "ASC"),
+            SortOrder::Desc => write!(f, "DESC"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ColumnConstraint {
+    PrimaryKey {
+        order: Option<SortOrder>,
+        autoincrement: bool,
+    },
+    NotNull,
+    Unique,
+    Check(String),
+    Default(DefaultValue),
+    Collate(String),
+    References(ForeignKey),
+    Generated {
+        expr: String,
+        stored: bool,
+    },
+}
+
+impl fmt::Display for ColumnConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnConstraint::PrimaryKey {
+                order,
+                autoincrement,
+            } => {
+                write!(f, "PRIMARY KEY")?;
+                if let Some(order) = order {
+                    write!(f, " {}", order)?;
+                }
+                if *autoincrement {
+                    write!(f, " AUTOINCREMENT")?;
+                }
+                Ok(())
+            }
+            ColumnConstraint::NotNull => write!(f, "NOT NULL"),
+            ColumnConstraint::Unique => write!(f, "UNIQUE"),
+            ColumnConstraint::Check(expr) => write!(f, "CHECK ({})", expr),
+            ColumnConstraint::Default(value) => write!(f, "DEFAULT {}", value),
+            ColumnConstraint::Collate(collation) => write!(f, "COLLATE {}", collation),
+            ColumnConstraint::References(foreign_key) => write!(f, "{}", foreign_key),
+            ColumnConstraint::Generated { expr, stored } => {
+                write!(f, "AS ({})", expr)?;
+                if *stored {
+                    write!(f, " STORED")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DefaultValue {
+    Literal(RType),
+    Expr(String),
+}
+
+impl fmt::Display for DefaultValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DefaultValue::Literal(RType::Null) => write!(f, "NULL"),
+            DefaultValue::Literal(RType::Str(value)) => {
+                write!(f, "'{}'", value.replace('\'', "''"))
+            }
+            DefaultValue::Literal(RType::Blob(value)) => {
+                write!(f, "{}", Token::Blob(value.clone()))
+            }
+            DefaultValue::Literal(value) => write!(f, "{}", value),
+            DefaultValue::Expr(expr) => write!(f, "({})", expr),
+        }
+    }
+}
+
+/// REFERENCES clause. Actions, MATCH and DEFERRABLE are not kept.
+#[derive(Debug)]
+pub struct ForeignKey {
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "REFERENCES {}", self.table)?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", self.columns.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum TableConstraint {
+    PrimaryKey(Vec<IndexedColumn>),
+    Unique(Vec<IndexedColumn>),
+    Check(String),
+    ForeignKey {
+        columns: Vec<String>,
+        references: ForeignKey,
+    },
+}
+
+impl fmt::Display for TableConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableConstraint::PrimaryKey(columns) => {
+                write!(f, "PRIMARY KEY ({})", columns.iter().join(", "))
+            }
+            TableConstraint::Unique(columns) => {
+                write!(f, "UNIQUE ({})", columns.iter().join(", "))
+            }
+            TableConstraint::Check(expr) => write!(f, "CHECK ({})", expr),
+            TableConstraint::ForeignKey {
+                columns,
+                references,
+            } => write!(f, "FOREIGN KEY ({}) {}", columns.join(", "), references),
+        }
+    }
+}
+
+/// Column of an index or of a PRIMARY KEY/UNIQUE constraint.
+/// For expressions, the name is the expression SQL text.
+#[derive(Debug, Clone)]
+pub struct IndexedColumn {
+    pub name: String,
+    pub collation: Option<String>,
+    pub order: Option<SortOrder>,
+}
+
+impl IndexedColumn {
+    /// Whether the B-tree is sorted in ascending order with the BINARY collation
+    /// on this column
+    pub fn is_binary_ascending(&self) -> bool {
+        let binary = self
+            .collation
+            .as_deref()
+            .is_none_or(|collation| collation.eq_ignore_ascii_case("binary"));
+        binary && self.order != Some(SortOrder::Desc)
+    }
+}
+
+impl fmt::Display for IndexedColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {}", collation)?;
+        }
+        if let Some(order) = &self.order {
+            write!(f, " {}", order)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, statement::Statement};
+
+    fn parse_create_table(query: &str) -> CreateTableStatement {
+        let Some(Ok(Statement::CreateTable(create_table))) = Parser::new(query).next() else {
+            panic!("Expected a CREATE TABLE statement");
+        };
+        create_table
+    }
+
+    #[test]
+    fn it_should_find_rowid_alias() {
+        let cases = [
+            (
+                "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)",
+                Some(0),
+            ),
+            (
+                "CREATE TABLE t (name TEXT, user_id integer primary key asc)",
+                Some(1),
+            ),
+            (
+                "CREATE TABLE t (a TEXT, b INTEGER, PRIMARY KEY (b DESC))",
+                Some(1),
+            ),
+            ("CREATE TABLE t (id TEXT, name TEXT)", None),
+            ("CREATE TABLE t (id INT PRIMARY KEY)", None),
+            ("CREATE TABLE t (id INTEGER PRIMARY KEY DESC)", None),
+            (
+                "CREATE TABLE t (a INTEGER, b INTEGER, PRIMARY KEY (a, b))",
+                None,
+            ),
+            (
+                "CREATE TABLE t (id INTEGER PRIMARY KEY) WITHOUT ROWID",
+                None,
+            ),
+        ];
+        for (query, rowid_alias) in cases {
+            assert_eq!(
+                parse_create_table(query).get_rowid_alias(),
+                rowid_alias,
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_list_primary_key_columns_in_key_order() {
+        let create_table = parse_create_table(
+            "CREATE TABLE t (a INTEGER, b TEXT COLLATE nocase, PRIMARY KEY (b, a DESC, b)) \
+             WITHOUT ROWID",
+        );
+        let primary_key = create_table
+            .get_primary_key()
+            .into_iter()
+            .map(|column| (column.name, column.collation, column.order))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            primary_key,
+            vec![
+                ("b".to_string(), Some("nocase".to_string()), None),
+                ("a".to_string(), None, Some(SortOrder::Desc)),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_compute_affinity_from_type_name() {
+        let cases = [
+            (Some("INTEGER"), Affinity::Integer),
+            (Some("unsigned big int"), Affinity::Integer),
+            (Some("VARCHAR(255)"), Affinity::Text),
+            (Some("BLOB"), Affinity::Blob),
+            (None, Affinity::Blob),
+            (Some("DOUBLE PRECISION"), Affinity::Real),
+            (Some("DECIMAL(10,5)"), Affinity::Numeric),
+            (Some("BOOLEAN"), Affinity::Numeric),
+            // INT is checked first: "FLOATING POINT" contains "INT"
+            (Some("FLOATING POINT"), Affinity::Integer),
+        ];
+        for (type_name, affinity) in cases {
+            assert_eq!(Affinity::from_type_name(type_name), affinity);
+        }
+    }
+}
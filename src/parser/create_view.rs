@@ -0,0 +1,28 @@
+//! CREATE VIEW statement, as stored in the sqlite_schema table.
+//!
+//! See [CREATE VIEW](https://www.sqlite.org/lang_createview.html).
+use crate::parser::select::SelectStatement;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct CreateViewStatement {
+    pub name: String,
+    pub if_not_exists: bool,
+    /// Names of the view columns, they rename the select columns
+    pub columns: Vec<String>,
+    pub select: SelectStatement,
+}
+
+impl fmt::Display for CreateViewStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CREATE VIEW ")?;
+        if self.if_not_exists {
+            write!(f, "IF NOT EXISTS ")?;
+        }
+        write!(f, "{}", self.name)?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", self.columns.join(", "))?;
+        }
+        write!(f, " AS {}", self.select)
+    }
+}
@@ -0,0 +1,492 @@
+//! Expressions used in the select list and the clauses of a query.
+//!
+//! Operators follow the sqlite precedence, from the highest to the lowest:
+//! * unary `-` `+`
+//! * `COLLATE`
+//! * `||`
+//! * `*` `/` `%`
+//! * `+` `-`
+//! * `<` `<=` `>` `>=`
+//! * `=` `!=` `IS` `IS NOT` `LIKE` `GLOB` `IN` `BETWEEN` `ISNULL` `NOTNULL`
+//! * `NOT`
+//! * `AND`
+//! * `OR`
+//!
+//! See [Operators](https://www.sqlite.org/lang_expr.html#operators_and_parse_affecting_attributes)
+use crate::executor::db_response::RType;
+use crate::parser::{
+    function::{FuncArgs, FuncCall},
+    token::Token,
+};
+use std::fmt;
+
+/// Precedence of `=` and of the predicates written with keywords, like `IN` or `LIKE`
+pub const EQUALITY_PRECEDENCE: u8 = 4;
+
+/// Precedence of the `COLLATE` postfix operator
+pub const COLLATE_PRECEDENCE: u8 = 10;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Literal(RType),
+    Column(String),
+    Unary {
+        operator: UnaryOperator,
+        expr: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: BinaryOperator,
+        right: Box<Expr>,
+    },
+    Function(FuncCall),
+    /// `expr [NOT] LIKE pattern [ESCAPE escape]`, and the ILIKE and GLOB variants
+    Like {
+        expr: Box<Expr>,
+        operator: LikeOperator,
+        pattern: Box<Expr>,
+        escape: Option<Box<Expr>>,
+        negated: bool,
+    },
+    /// `expr [NOT] IN (value, ...)`
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    /// `expr [NOT] BETWEEN low AND high`
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    /// `expr COLLATE name`, the collation used when the expression is compared
+    Collate {
+        expr: Box<Expr>,
+        collation: String,
+    },
+}
+
+impl Expr {
+    pub fn binary(left: Expr, operator: BinaryOperator, right: Expr) -> Self {
+        Expr::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    /// Aggregate function calls of the expression.
+    /// The arguments of an aggregate call are not searched.
+    pub fn get_aggregates(&self) -> Vec<&FuncCall> {
+        match self {
+            Expr::Function(func) if func.is_aggregate() => vec![func],
+            expr => expr
+                .children()
+                .into_iter()
+                .flat_map(Expr::get_aggregates)
+                .collect(),
+        }
+    }
+
+    pub fn contains_aggregate(&self) -> bool {
+        !self.get_aggregates().is_empty()
+    }
+
+    /// Names of the columns referenced by the expression
+    pub fn get_columns(&self) -> Vec<&str> {
+        match self {
+            Expr::Column(name) => vec![name],
+            expr => expr
+                .children()
+                .into_iter()
+                .flat_map(Expr::get_columns)
+                .collect(),
+        }
+    }
+
+    /// Conditions that must all hold for the expression to be true:
+    /// the expression itself or the operands of its top level ANDs
+    pub fn get_conditions(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Binary {
+                left,
+                operator: BinaryOperator::And,
+                right,
+            } => {
+                let mut conditions = left.get_conditions();
+                conditions.extend(right.get_conditions());
+                conditions
+            }
+            expr => vec![expr],
+        }
+    }
+
+    /// Copy of the expression where each sub-expression for which `replace` returns
+    /// an expression is replaced by it
+    pub fn transform(&self, replace: &impl Fn(&Expr) -> Option<Expr>) -> Expr {
+        if let Some(expr) = replace(self) {
+            return expr;
+        }
+        let transform = |expr: &Expr| Box::new(expr.transform(replace));
+        match self {
+            Expr::Literal(_) | Expr::Column(_) => self.clone(),
+            Expr::Unary { operator, expr } => Expr::Unary {
+                operator: *operator,
+                expr: transform(expr),
+            },
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => Expr::Binary {
+                left: transform(left),
+                operator: *operator,
+                right: transform(right),
+            },
+            Expr::Function(func) => Expr::Function(FuncCall {
+                function_name: func.function_name.clone(),
+                args: match &func.args {
+                    FuncArgs::Star => FuncArgs::Star,
+                    FuncArgs::List(args) => {
+                        FuncArgs::List(args.iter().map(|arg| arg.transform(replace)).collect())
+                    }
+                },
+                distinct: func.distinct,
+            }),
+            Expr::Like {
+                expr,
+                operator,
+                pattern,
+                escape,
+                negated,
+            } => Expr::Like {
+                expr: transform(expr),
+                operator: *operator,
+                pattern: transform(pattern),
+                escape: escape.as_deref().map(transform),
+                negated: *negated,
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Expr::InList {
+                expr: transform(expr),
+                list: list.iter().map(|expr| expr.transform(replace)).collect(),
+                negated: *negated,
+            },
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => Expr::Between {
+                expr: transform(expr),
+                low: transform(low),
+                high: transform(high),
+                negated: *negated,
+            },
+            Expr::Collate { expr, collation } => Expr::Collate {
+                expr: transform(expr),
+                collation: collation.clone(),
+            },
+        }
+    }
+
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Literal(_) | Expr::Column(_) => vec![],
+            Expr::Unary { expr, .. } | Expr::Collate { expr, .. } => vec![expr],
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Function(func) => match &func.args {
+                FuncArgs::Star => vec![],
+                FuncArgs::List(args) => args.iter().collect(),
+            },
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => [Some(expr), Some(pattern), escape.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|expr| expr.as_ref())
+                .collect(),
+            Expr::InList { expr, list, .. } => std::iter::once(expr.as_ref()).chain(list).collect(),
+            Expr::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+        }
+    }
+
+    // Precedence used to know when parentheses are needed to display the expression
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary { operator, .. } => operator.precedence(),
+            Expr::Unary { operator, .. } => operator.precedence(),
+            Expr::Like { .. } | Expr::InList { .. } | Expr::Between { .. } => EQUALITY_PRECEDENCE,
+            Expr::Collate { .. } => COLLATE_PRECEDENCE,
+            _ => u8::MAX,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Literal(RType::Null) => write!(f, "NULL"),
+            Expr::Literal(RType::Str(value)) => write!(f, "'{}'", value.replace('\'', "''")),
+            Expr::Literal(RType::Real(value)) => write!(f, "{:?}", value),
+            Expr::Literal(RType::Blob(value)) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "'")
+            }
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Column(name) => write!(f, "{}", name),
+            Expr::Unary { operator, expr } => {
+                write!(f, "{}", operator)?;
+                expr.fmt_operand(f, operator.precedence())
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                // Operators are left associative: the right operand needs parentheses
+                // when it has the same precedence, `a - (b - c)`
+                left.fmt_operand(f, operator.precedence())?;
+                write!(f, " {} ", operator)?;
+                right.fmt_operand(f, operator.precedence() + 1)
+            }
+            Expr::Function(func) => write!(f, "{}", func),
+            Expr::Like {
+                expr,
+                operator,
+                pattern,
+                escape,
+                negated,
+            } => {
+                expr.fmt_operand(f, EQUALITY_PRECEDENCE)?;
+                if *negated {
+                    write!(f, " NOT")?;
+                }
+                write!(f, " {} ", operator)?;
+                pattern.fmt_operand(f, EQUALITY_PRECEDENCE + 1)?;
+                if let Some(escape) = escape {
+                    write!(f, " ESCAPE ")?;
+                    escape.fmt_operand(f, EQUALITY_PRECEDENCE + 1)?;
+                }
+                Ok(())
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                expr.fmt_operand(f, EQUALITY_PRECEDENCE)?;
+                write!(f, "{} IN (", if *negated { " NOT" } else { "" })?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                expr.fmt_operand(f, EQUALITY_PRECEDENCE)?;
+                write!(f, "{} BETWEEN ", if *negated { " NOT" } else { "" })?;
+                low.fmt_operand(f, EQUALITY_PRECEDENCE + 1)?;
+                write!(f, " AND ")?;
+                high.fmt_operand(f, EQUALITY_PRECEDENCE + 1)
+            }
+            Expr::Collate { expr, collation } => {
+                expr.fmt_operand(f, COLLATE_PRECEDENCE)?;
+                write!(f, " COLLATE {}", collation)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryOperator {
+    Minus,
+    Plus,
+    Not,
+}
+
+impl UnaryOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::Minus => Some(UnaryOperator::Minus),
+            Token::Plus => Some(UnaryOperator::Plus),
+            Token::Not => Some(UnaryOperator::Not),
+            _ => None,
+        }
+    }
+
+    /// `NOT` binds looser than comparisons: `NOT a = b` is `NOT (a = b)`
+    pub fn precedence(&self) -> u8 {
+        match self {
+            UnaryOperator::Minus | UnaryOperator::Plus => 11,
+            UnaryOperator::Not => 3,
+        }
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOperator::Minus => write!(f, "-"),
+            UnaryOperator::Plus => write!(f, "+"),
+            UnaryOperator::Not => write!(f, "NOT "),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOperator {
+    Concat,
+    Mul,
+    Div,
+    Mod,
+    Add,
+    Sub,
+    LT,
+    LTE,
+    GT,
+    GTE,
+    Eq,
+    NotEq,
+    Is,
+    IsNot,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        let operator = match token {
+            Token::Concat => BinaryOperator::Concat,
+            Token::Star => BinaryOperator::Mul,
+            Token::Div => BinaryOperator::Div,
+            Token::Mod => BinaryOperator::Mod,
+            Token::Plus => BinaryOperator::Add,
+            Token::Minus => BinaryOperator::Sub,
+            Token::LT => BinaryOperator::LT,
+            Token::LTEQ => BinaryOperator::LTE,
+            Token::GT => BinaryOperator::GT,
+            Token::GTEQ => BinaryOperator::GTE,
+            Token::Equal => BinaryOperator::Eq,
+            Token::NotEq => BinaryOperator::NotEq,
+            // `IS NOT` is read by the parser after `IS`
+            Token::Is => BinaryOperator::Is,
+            Token::And => BinaryOperator::And,
+            Token::Or => BinaryOperator::Or,
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    /// The higher the precedence, the tighter the operator binds its operands.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Concat => 9,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 8,
+            BinaryOperator::Add | BinaryOperator::Sub => 7,
+            BinaryOperator::LT | BinaryOperator::LTE | BinaryOperator::GT | BinaryOperator::GTE => {
+                5
+            }
+            BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Is
+            | BinaryOperator::IsNot => EQUALITY_PRECEDENCE,
+            BinaryOperator::And => 2,
+            BinaryOperator::Or => 1,
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(self.precedence(), 4 | 5)
+    }
+
+    /// Operator to use when the operands are swapped: `5 < a` is `a > 5`
+    pub fn flip(self) -> Self {
+        match self {
+            BinaryOperator::LT => BinaryOperator::GT,
+            BinaryOperator::GT => BinaryOperator::LT,
+            BinaryOperator::LTE => BinaryOperator::GTE,
+            BinaryOperator::GTE => BinaryOperator::LTE,
+            operator => operator,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryOperator::Concat => write!(f, "||"),
+            BinaryOperator::Mul => write!(f, "*"),
+            BinaryOperator::Div => write!(f, "/"),
+            BinaryOperator::Mod => write!(f, "%"),
+            BinaryOperator::Add => write!(f, "+"),
+            BinaryOperator::Sub => write!(f, "-"),
+            BinaryOperator::LT => write!(f, "<"),
+            BinaryOperator::LTE => write!(f, "<="),
+            BinaryOperator::GT => write!(f, ">"),
+            BinaryOperator::GTE => write!(f, ">="),
+            BinaryOperator::Eq => write!(f, "="),
+            BinaryOperator::NotEq => write!(f, "!="),
+            BinaryOperator::Is => write!(f, "IS"),
+            BinaryOperator::IsNot => write!(f, "IS NOT"),
+            BinaryOperator::And => write!(f, "AND"),
+            BinaryOperator::Or => write!(f, "OR"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LikeOperator {
+    Like,
+    ILike,
+    Glob,
+}
+
+impl LikeOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::Like => Some(LikeOperator::Like),
+            Token::ILike => Some(LikeOperator::ILike),
+            Token::Glob => Some(LikeOperator::Glob),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LikeOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LikeOperator::Like => write!(f, "LIKE"),
+            LikeOperator::ILike => write!(f, "ILIKE"),
+            LikeOperator::Glob => write!(f, "GLOB"),
+        }
+    }
+}
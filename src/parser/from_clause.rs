@@ -0,0 +1,127 @@
+//! FROM clause of a select statement: the tables it reads and how they are joined.
+//!
+//! See [join-clause](https://www.sqlite.org/syntax/join-clause.html).
+use crate::parser::expr::Expr;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct FromClause {
+    pub table: TableRef,
+    /// Tables joined to the tables on their left, in order
+    pub joins: Vec<Join>,
+}
+
+impl FromClause {
+    pub fn new(table: TableRef) -> Self {
+        Self {
+            table,
+            joins: vec![],
+        }
+    }
+
+    /// Tables of the clause in join order
+    pub fn get_tables(&self) -> impl Iterator<Item = &TableRef> {
+        std::iter::once(&self.table).chain(self.joins.iter().map(|join| &join.table))
+    }
+}
+
+impl fmt::Display for FromClause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FROM {}", self.table)?;
+        for join in self.joins.iter() {
+            write!(f, "{}", join)?;
+        }
+        Ok(())
+    }
+}
+
+/// `name [[AS] alias]`
+#[derive(Debug, Clone)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableRef {
+    /// Name qualifying the columns of the table: the alias when there is one
+    pub fn get_qualifier(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.alias {
+            Some(alias) => write!(f, "{} AS {}", self.name, alias),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Join {
+    pub operator: JoinOperator,
+    /// NATURAL joins compare the columns with the same name in both tables
+    pub natural: bool,
+    pub table: TableRef,
+    pub constraint: Option<JoinConstraint>,
+}
+
+impl Join {
+    /// Rows of the left tables without a matching row are kept with NULLs
+    pub fn is_left(&self) -> bool {
+        self.operator == JoinOperator::Left
+    }
+}
+
+impl fmt::Display for Join {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.operator == JoinOperator::Comma {
+            return write!(f, ", {}", self.table);
+        }
+        if self.natural {
+            write!(f, " NATURAL")?;
+        }
+        write!(f, " {} {}", self.operator, self.table)?;
+        if let Some(constraint) = &self.constraint {
+            write!(f, " {}", constraint)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JoinOperator {
+    /// `a, b` is the same as `a JOIN b`
+    Comma,
+    Inner,
+    Left,
+    Cross,
+}
+
+impl fmt::Display for JoinOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinOperator::Comma => write!(f, ","),
+            JoinOperator::Inner => write!(f, "JOIN"),
+            JoinOperator::Left => write!(f, "LEFT JOIN"),
+            JoinOperator::Cross => write!(f, "CROSS JOIN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JoinConstraint {
+    On(Expr),
+    /// Columns with the same name in both tables that must be equal
+    Using(Vec<String>),
+}
+
+impl fmt::Display for JoinConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinConstraint::On(expr) => write!(f, "ON {}", expr),
+            JoinConstraint::Using(columns) => write!(f, "USING ({})", columns.join(", ")),
+        }
+    }
+}
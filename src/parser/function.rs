@@ -1,25 +1,61 @@
-use crate::parser::select::SelectItem;
+use crate::parser::expr::Expr;
 use itertools::Itertools;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct FuncCall {
     pub function_name: String,
-    pub(self) params: Vec<SelectItem>,
+    pub args: FuncArgs,
+    /// `count(DISTINCT x)`: an aggregate only uses each value once
+    pub distinct: bool,
 }
 
 impl FuncCall {
-    pub fn new(function_name: String, params: Vec<SelectItem>) -> Self {
+    pub fn new(function_name: String, args: FuncArgs) -> Self {
         Self {
             function_name,
-            params,
+            args,
+            distinct: false,
+        }
+    }
+
+    /// Aggregate functions are computed over all the rows instead of each row.
+    /// min and max with several arguments are not aggregates, they compare their arguments.
+    pub fn is_aggregate(&self) -> bool {
+        match (self.function_name.as_str(), &self.args) {
+            ("count" | "sum" | "total" | "avg" | "group_concat", _) => true,
+            ("min" | "max", FuncArgs::List(args)) => args.len() == 1,
+            ("min" | "max", FuncArgs::Star) => true,
+            _ => false,
         }
     }
 }
 
 impl fmt::Display for FuncCall {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let identifiers = self.params.iter().join(", ");
-        write!(f, "{}({})", self.function_name.to_uppercase(), identifiers)
+        let distinct = if self.distinct { "DISTINCT " } else { "" };
+        write!(
+            f,
+            "{}({}{})",
+            self.function_name.to_uppercase(),
+            distinct,
+            self.args
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FuncArgs {
+    // count(*)
+    Star,
+    List(Vec<Expr>),
+}
+
+impl fmt::Display for FuncArgs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FuncArgs::Star => write!(f, "*"),
+            FuncArgs::List(args) => write!(f, "{}", args.iter().join(", ")),
+        }
     }
 }
@@ -0,0 +1,59 @@
+//! ORDER BY clause of a select statement.
+//!
+//! See [ORDER BY](https://www.sqlite.org/lang_select.html#the_order_by_clause).
+use crate::parser::{create_table::SortOrder, expr::Expr};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct OrderingTerm {
+    /// An integer literal is the position of a result column, starting at 1
+    pub expr: Expr,
+    pub collation: Option<String>,
+    pub order: Option<SortOrder>,
+    pub nulls: Option<NullsOrder>,
+}
+
+impl OrderingTerm {
+    pub fn is_descending(&self) -> bool {
+        self.order == Some(SortOrder::Desc)
+    }
+
+    /// NULLs are the smallest values: they come first unless the order is descending
+    pub fn nulls_first(&self) -> bool {
+        match self.nulls {
+            Some(nulls) => nulls == NullsOrder::First,
+            None => !self.is_descending(),
+        }
+    }
+}
+
+impl fmt::Display for OrderingTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {}", collation)?;
+        }
+        if let Some(order) = &self.order {
+            write!(f, " {}", order)?;
+        }
+        if let Some(nulls) = &self.nulls {
+            write!(f, " {}", nulls)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+impl fmt::Display for NullsOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NullsOrder::First => write!(f, "NULLS FIRST"),
+            NullsOrder::Last => write!(f, "NULLS LAST"),
+        }
+    }
+}
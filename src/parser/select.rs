@@ -1,122 +1,173 @@
-use crate::parser::identifier::Identifier;
 use crate::parser::token::Token;
-use crate::parser::{function::FuncCall, where_clause::Where};
-use anyhow::{Result, anyhow};
+use crate::parser::{
+    expr::Expr, from_clause::FromClause, function::FuncCall, order_by::OrderingTerm,
+    where_clause::Where,
+};
 use itertools::Itertools;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SelectStatement {
     pub select_clause: SelectClause,
-    pub from_clause: String,
+    pub from_clause: Option<FromClause>,
     pub where_clause: Option<Where>,
+    /// An integer literal is the position of a result column, starting at 1
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    pub order_by: Vec<OrderingTerm>,
+    pub limit: Option<Limit>,
 }
 
 impl SelectStatement {
     pub fn new(
         select_clause: SelectClause,
-        from_clause: String,
+        from_clause: Option<FromClause>,
         where_clause: Option<Where>,
     ) -> Self {
         Self {
             select_clause,
             from_clause,
             where_clause,
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: None,
         }
     }
 
-    pub fn add_from(&mut self, value: String) {
-        self.from_clause = value;
+    pub fn add_from(&mut self, from_clause: FromClause) {
+        self.from_clause = Some(from_clause);
+    }
+
+    /// An aggregate query has a GROUP BY clause or aggregate calls in its result columns
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || self.result_exprs().any(Expr::contains_aggregate)
+    }
+
+    /// Aggregate function calls of the result columns, HAVING and ORDER BY, without duplicates
+    pub fn get_aggregates(&self) -> Vec<&FuncCall> {
+        let exprs = self
+            .result_exprs()
+            .chain(self.having.iter())
+            .chain(self.order_by.iter().map(|term| &term.expr));
+        let mut aggregates = vec![];
+        for func in exprs.flat_map(Expr::get_aggregates) {
+            if !aggregates.contains(&func) {
+                aggregates.push(func);
+            }
+        }
+        aggregates
+    }
+
+    fn result_exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.select_clause
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Expr { expr, .. } => Some(expr),
+                SelectItem::Star | SelectItem::TableStar(_) => None,
+            })
     }
 }
 
 impl fmt::Display for SelectStatement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.select_clause)?;
-        if self.from_clause.len() > 0 {
-            write!(f, " FROM {}", self.from_clause)?;
+        if let Some(from_clause) = &self.from_clause {
+            write!(f, " {}", from_clause)?;
         }
 
         if let Some(where_clause) = &self.where_clause {
             write!(f, " {}", where_clause)?;
         }
+        if !self.group_by.is_empty() {
+            write!(f, " GROUP BY {}", self.group_by.iter().join(", "))?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {}", having)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY {}", self.order_by.iter().join(", "))?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " {}", limit)?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+/// `LIMIT limit [OFFSET offset]`, `LIMIT offset, limit` is the same clause
+#[derive(Debug, Clone)]
+pub struct Limit {
+    pub limit: Expr,
+    pub offset: Option<Expr>,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LIMIT {}", self.limit)?;
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SelectClause {
     token: Token,
+    /// `SELECT DISTINCT`: duplicate result rows are removed
+    pub distinct: bool,
     pub items: Vec<SelectItem>,
 }
 
 impl SelectClause {
     pub fn new(token: Token) -> Self {
         Self {
-            token: token,
+            token,
+            distinct: false,
             items: vec![],
         }
     }
 
-    pub fn is_function(&self) -> bool {
-        self.items.iter().any(|item| {
-            if let SelectItem::Function(_) = item {
-                return true;
-            }
-            false
-        });
-        false
-    }
-
-    pub fn get_function(&self) -> Option<&FuncCall> {
-        for item in self.items.iter() {
-            if let SelectItem::Function(func) = item {
-                return Some(&func);
-            }
-        }
-        None
-    }
-
     pub fn push_item(&mut self, item: SelectItem) {
         self.items.push(item);
     }
-
-    pub fn check_select_clause(self) -> Result<()> {
-        if self.items.len() > 1 {
-            match self.items[0] {
-                SelectItem::Function(_) => {
-                    return Err(anyhow!("Executor: select clause mixes function and values"));
-                }
-                SelectItem::Star => {
-                    return Err(anyhow!("Executor: select clause has * and other values"));
-                }
-                _ => {}
-            }
-        }
-        Ok(())
-    }
 }
 
 impl fmt::Display for SelectClause {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let identifiers = self.items.iter().join(", ");
-        write!(f, "{} {}", self.token, identifiers)
+        if self.distinct {
+            write!(f, "{} DISTINCT {}", self.token, identifiers)
+        } else {
+            write!(f, "{} {}", self.token, identifiers)
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SelectItem {
-    Function(FuncCall),
-    Identifier(Identifier),
+    /// `expr [[AS] alias]`, the alias names the result column
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+    },
     Star,
+    /// `table.*`, the columns of one table of the FROM clause
+    TableStar(String),
 }
 
 impl fmt::Display for SelectItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            SelectItem::Function(func) => write!(f, "{}", func),
-            SelectItem::Identifier(ident) => write!(f, "{}", ident),
+            SelectItem::Expr {
+                expr,
+                alias: Some(alias),
+            } => write!(f, "{} AS {}", expr, alias),
+            SelectItem::Expr { expr, alias: None } => write!(f, "{}", expr),
             SelectItem::Star => write!(f, "*"),
+            SelectItem::TableStar(table) => write!(f, "{}.*", table),
         }
     }
 }
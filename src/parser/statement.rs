@@ -3,18 +3,25 @@
 //! * select
 //! * update
 //! * insert
+//! * create table, create index and create view, to read the database schema
 //!
 //! For each statement, there are the following clauses:
 //! * Select => mandatory, not to confuse with the statement type
 //! * From
 //! * Where
 //!
-use crate::parser::{select::SelectStatement, token::Command};
+use crate::parser::{
+    create_index::CreateIndexStatement, create_table::CreateTableStatement,
+    create_view::CreateViewStatement, select::SelectStatement, token::Command,
+};
 use std::fmt;
 
 #[derive(Debug)]
 pub enum Statement {
     Select(SelectStatement),
+    CreateTable(CreateTableStatement),
+    CreateIndex(CreateIndexStatement),
+    CreateView(CreateViewStatement),
     Command(Command),
 }
 
@@ -22,6 +29,9 @@ impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Statement::Select(statement) => write!(f, "{}", statement),
+            Statement::CreateTable(statement) => write!(f, "{}", statement),
+            Statement::CreateIndex(statement) => write!(f, "{}", statement),
+            Statement::CreateView(statement) => write!(f, "{}", statement),
             Statement::Command(cmd) => write!(f, "{}", cmd),
         }
     }
@@ -1,21 +1,60 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Error, Result, anyhow};
 use std::fmt;
+use std::str::FromStr;
 
 use crate::executor::db_response::RType;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     Illegal(String),
     Select,
     From,
     Where,
+    Order,
+    By,
+    Limit,
+    Offset,
+    Group,
+    Having,
+    Distinct,
+    All,
     Null,
     Not,
+    And,
+    Or,
+    Is,
+    IsNull,
+    NotNull,
+    In,
+    Between,
     Like,
     ILike,
+    Glob,
+    Create,
+    Table,
+    Index,
+    Unique,
+    Primary,
+    Check,
+    Default,
+    Collate,
+    References,
+    Constraint,
+    Foreign,
+    As,
+    On,
+    Join,
+    Left,
+    Outer,
+    Inner,
+    Cross,
+    Natural,
+    Using,
     Ident(String),
     QIdent(String),
     Num(i64),
+    Real(f64),
+    Blob(Vec<u8>),
     Coma,
     SemiColon,
     RParen,
@@ -30,20 +69,60 @@ pub enum Token {
     Plus,
     Minus,
     Div,
+    Mod,
+    Concat,
     Command(Command),
 }
 
-impl Token {
-    pub fn from_str(str: &str) -> Result<Self> {
+impl FromStr for Token {
+    type Err = Error;
+
+    fn from_str(str: &str) -> Result<Self> {
         let lower_str = str.to_lowercase();
         let token = match lower_str.as_str() {
             "where" => Token::Where,
+            "order" => Token::Order,
+            "by" => Token::By,
+            "limit" => Token::Limit,
+            "offset" => Token::Offset,
+            "group" => Token::Group,
+            "having" => Token::Having,
+            "distinct" => Token::Distinct,
+            "all" => Token::All,
             "select" => Token::Select,
             "from" => Token::From,
             "null" => Token::Null,
             "not" => Token::Not,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "is" => Token::Is,
+            "isnull" => Token::IsNull,
+            "notnull" => Token::NotNull,
+            "in" => Token::In,
+            "between" => Token::Between,
             "like" => Token::Like,
             "ilike" => Token::ILike,
+            "glob" => Token::Glob,
+            "create" => Token::Create,
+            "table" => Token::Table,
+            "index" => Token::Index,
+            "unique" => Token::Unique,
+            "primary" => Token::Primary,
+            "check" => Token::Check,
+            "default" => Token::Default,
+            "collate" => Token::Collate,
+            "references" => Token::References,
+            "constraint" => Token::Constraint,
+            "foreign" => Token::Foreign,
+            "as" => Token::As,
+            "on" => Token::On,
+            "join" => Token::Join,
+            "left" => Token::Left,
+            "outer" => Token::Outer,
+            "inner" => Token::Inner,
+            "cross" => Token::Cross,
+            "natural" => Token::Natural,
+            "using" => Token::Using,
             "," => Token::Coma,
             ";" => Token::SemiColon,
             "(" => Token::LParen,
@@ -58,13 +137,29 @@ impl Token {
             "+" => Token::Plus,
             "-" => Token::Minus,
             "/" => Token::Div,
+            "%" => Token::Mod,
+            "||" => Token::Concat,
             _ => {
                 let first_char = lower_str
                     .chars()
                     .next()
                     .expect("We know that there are at least one char");
-                if first_char.is_numeric() {
-                    Token::Num(lower_str.parse::<i64>()?)
+                if let Some(hex) = lower_str.strip_prefix("0x") {
+                    // Hexadecimal integers are 64-bit two's complement: 0xffffffffffffffff is -1
+                    Token::Num(u64::from_str_radix(hex, 16)? as i64)
+                } else if first_char.is_ascii_digit()
+                    || lower_str.starts_with(".")
+                        && lower_str[1..].starts_with(|c: char| c.is_ascii_digit())
+                {
+                    if lower_str.contains(['.', 'e']) {
+                        Token::Real(lower_str.parse::<f64>()?)
+                    } else {
+                        match lower_str.parse::<i64>() {
+                            Ok(value) => Token::Num(value),
+                            // Integers too big for 64 bits are reals
+                            Err(_) => Token::Real(lower_str.parse::<f64>()?),
+                        }
+                    }
                 } else if first_char == '.' {
                     let cmd = Command::from_str(&lower_str)?;
                     Token::Command(cmd)
@@ -79,11 +174,15 @@ impl Token {
         };
         Ok(token)
     }
+}
 
+impl Token {
     pub fn into_rtype(&self) -> RType {
         match self {
             Token::Num(value) => RType::Num(*value),
+            Token::Real(value) => RType::Real(*value),
             Token::QIdent(value) => RType::Str(value.to_string()),
+            Token::Blob(value) => RType::Blob(value.clone()),
             _ => panic!("Should never transform {} into RType", self),
         }
     }
@@ -94,17 +193,61 @@ impl fmt::Display for Token {
         match self {
             Token::Select => write!(f, "SELECT"),
             Token::Where => write!(f, "WHERE"),
+            Token::Order => write!(f, "ORDER"),
+            Token::By => write!(f, "BY"),
+            Token::Limit => write!(f, "LIMIT"),
+            Token::Offset => write!(f, "OFFSET"),
+            Token::Group => write!(f, "GROUP"),
+            Token::Having => write!(f, "HAVING"),
+            Token::Distinct => write!(f, "DISTINCT"),
+            Token::All => write!(f, "ALL"),
             Token::From => write!(f, "FROM"),
             Token::Not => write!(f, "NOT"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Is => write!(f, "IS"),
+            Token::IsNull => write!(f, "ISNULL"),
+            Token::NotNull => write!(f, "NOTNULL"),
+            Token::In => write!(f, "IN"),
+            Token::Between => write!(f, "BETWEEN"),
             Token::Like => write!(f, "LIKE"),
             Token::ILike => write!(f, "ILIKE"),
-            Token::RParen => write!(f, "("),
-            Token::LParen => write!(f, ")"),
+            Token::Glob => write!(f, "GLOB"),
+            Token::Create => write!(f, "CREATE"),
+            Token::Table => write!(f, "TABLE"),
+            Token::Index => write!(f, "INDEX"),
+            Token::Unique => write!(f, "UNIQUE"),
+            Token::Primary => write!(f, "PRIMARY"),
+            Token::Check => write!(f, "CHECK"),
+            Token::Default => write!(f, "DEFAULT"),
+            Token::Collate => write!(f, "COLLATE"),
+            Token::References => write!(f, "REFERENCES"),
+            Token::Constraint => write!(f, "CONSTRAINT"),
+            Token::Foreign => write!(f, "FOREIGN"),
+            Token::As => write!(f, "AS"),
+            Token::On => write!(f, "ON"),
+            Token::Join => write!(f, "JOIN"),
+            Token::Left => write!(f, "LEFT"),
+            Token::Outer => write!(f, "OUTER"),
+            Token::Inner => write!(f, "INNER"),
+            Token::Cross => write!(f, "CROSS"),
+            Token::Natural => write!(f, "NATURAL"),
+            Token::Using => write!(f, "USING"),
+            Token::RParen => write!(f, ")"),
+            Token::LParen => write!(f, "("),
             Token::Coma => write!(f, ","),
             Token::SemiColon => write!(f, ";"),
             Token::Ident(value) => write!(f, "{}", value),
-            Token::QIdent(value) => write!(f, "'{}'", value),
+            Token::QIdent(value) => write!(f, "'{}'", value.replace('\'', "''")),
             Token::Num(value) => write!(f, "{}", value),
+            Token::Real(value) => write!(f, "{:?}", value),
+            Token::Blob(value) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02X}", byte)?;
+                }
+                write!(f, "'")
+            }
             Token::Star => write!(f, "*"),
             Token::Null => write!(f, "NULL"),
             Token::Equal => write!(f, "="),
@@ -116,20 +259,24 @@ impl fmt::Display for Token {
             Token::Plus => write!(f, "+"),
             Token::Minus => write!(f, "-"),
             Token::Div => write!(f, "/"),
+            Token::Mod => write!(f, "%"),
+            Token::Concat => write!(f, "||"),
             Token::Illegal(value) => write!(f, "Illegal token: {}", value),
             Token::Command(command) => write!(f, "Command: {}", command),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Command {
     DBinfo,
     Tables,
 }
 
-impl Command {
-    pub fn from_str(command: &str) -> Result<Self> {
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(command: &str) -> Result<Self> {
         let command = match command {
             ".dbinfo" => Command::DBinfo,
             ".tables" => Command::Tables,
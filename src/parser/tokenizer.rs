@@ -1,12 +1,12 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use std::iter::{Iterator, Peekable};
-use std::str::Chars;
+use std::str::{Chars, FromStr};
 
 use crate::parser::token::Token;
 
 pub struct Tokenizer<'a> {
     buffer: Peekable<Chars<'a>>,
-    peeked: Option<Token>,
+    peeked: Option<Result<Token>>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -17,32 +17,96 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    // Returns the next token without consuming it
-    pub fn peek(&mut self) -> Option<Result<&Token>> {
-        // TODO: Refactor the peek
-        if let Some(ref peeked) = self.peeked {
-            return Some(Ok(peeked));
+    // Returns the next token without consuming it, an error is kept until `next` returns it
+    pub fn peek(&mut self) -> Option<Result<&Token, &anyhow::Error>> {
+        if self.peeked.is_none() {
+            self.peeked = self.next();
         }
-        if let Some(Ok(next)) = self.next() {
-            self.peeked = Some(next);
-        }
-        if let Some(ref peeked) = self.peeked {
-            return Some(Ok(peeked));
+        self.peeked.as_ref().map(|peeked| peeked.as_ref())
+    }
+
+    fn trim_space(&mut self) {
+        while self.buffer.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    // Skip a `-- comment` up to the end of the line
+    fn skip_line_comment(&mut self) {
+        for next in self.buffer.by_ref() {
+            if next == '\n' {
+                break;
+            }
         }
+    }
 
-        None
+    // Skip a `/* comment */`, the opening `/*` is already consumed
+    fn skip_block_comment(&mut self) {
+        while let Some(next) = self.buffer.next() {
+            if next == '*' && self.buffer.next_if_eq(&'/').is_some() {
+                break;
+            }
+        }
     }
 
-    fn trim_space(&mut self) {
+    // Read a quoted string up to the closing quote.
+    // A doubled quote inside the string stands for the quote itself: 'it''s'
+    fn read_quoted(&mut self, closing_quote: char) -> Result<String> {
+        let mut value = String::new();
         loop {
-            match self.buffer.peek() {
-                Some(' ') => {
-                    self.buffer.next();
+            match self.buffer.next() {
+                Some(next) if next == closing_quote => {
+                    if self.buffer.next_if_eq(&closing_quote).is_some() {
+                        value.push(closing_quote);
+                    } else {
+                        return Ok(value);
+                    }
                 }
-                Some(_) => break,
-                _ => break,
+                Some(next) => value.push(next),
+                None => return Err(anyhow!("Unterminated quoted value: {}", value)),
+            }
+        }
+    }
+
+    // Read a number literal starting with `first`: digits with an optional fractional
+    // part and exponent, or a hexadecimal integer. The sign is a separate token.
+    fn read_number(&mut self, first: char) -> Result<Token> {
+        let mut number = first.to_string();
+        if first == '0'
+            && let Some(x) = self.buffer.next_if(|c| *c == 'x' || *c == 'X')
+        {
+            number.push(x);
+            self.read_while(&mut number, char::is_ascii_alphanumeric);
+            return Token::from_str(&number);
+        }
+        self.read_while(&mut number, char::is_ascii_digit);
+        if first != '.'
+            && let Some(dot) = self.buffer.next_if_eq(&'.')
+        {
+            number.push(dot);
+            self.read_while(&mut number, char::is_ascii_digit);
+        }
+        if let Some(e) = self.buffer.next_if(|c| *c == 'e' || *c == 'E') {
+            number.push(e);
+            if let Some(sign) = self.buffer.next_if(|c| *c == '+' || *c == '-') {
+                number.push(sign);
+            }
+            if !self.read_while(&mut number, char::is_ascii_digit) {
+                return Err(anyhow!("Unrecognized token: {}", number));
             }
         }
+        // A number directly followed by letters is not a token: 12abc
+        if self.read_while(&mut number, |c| c.is_alphanumeric() || *c == '_') {
+            return Err(anyhow!("Unrecognized token: {}", number));
+        }
+        Token::from_str(&number)
+    }
+
+    // Push the characters matching `predicate` to `value`, returns whether there was any
+    fn read_while(&mut self, value: &mut String, predicate: impl Fn(&char) -> bool) -> bool {
+        let len = value.len();
+        while let Some(next) = self.buffer.next_if(&predicate) {
+            value.push(next);
+        }
+        value.len() > len
     }
 }
 
@@ -53,23 +117,44 @@ impl Iterator for Tokenizer<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         // Return peeked value saved when used the method Tokenizer.peek()
         if let Some(peeked) = self.peeked.take() {
-            return Some(Ok(peeked));
-        }
-        if let None = self.buffer.peek() {
-            return None;
+            return Some(peeked);
         }
         self.trim_space();
-        let mut next = self.buffer.next().unwrap();
+        let mut next = self.buffer.next()?;
         let token = match next {
             ';' => Token::from_str(";"),
             ',' => Token::from_str(","),
-            '(' => Token::from_str("()"),
+            '(' => Token::from_str("("),
             ')' => Token::from_str(")"),
             '+' => Token::from_str("+"),
-            '-' => Token::from_str("-"),
-            '/' => Token::from_str("/"),
+            '-' => {
+                if self.buffer.next_if_eq(&'-').is_some() {
+                    self.skip_line_comment();
+                    return self.next();
+                }
+                Token::from_str("-")
+            }
+            '/' => {
+                if self.buffer.next_if_eq(&'*').is_some() {
+                    self.skip_block_comment();
+                    return self.next();
+                }
+                Token::from_str("/")
+            }
             '*' => Token::from_str("*"),
-            '=' => Token::from_str("="),
+            '%' => Token::from_str("%"),
+            '|' => {
+                if self.buffer.next_if_eq(&'|').is_some() {
+                    Token::from_str("||")
+                } else {
+                    Ok(Token::Illegal("|".to_string()))
+                }
+            }
+            '=' => {
+                // `==` is the same operator as `=`
+                self.buffer.next_if_eq(&'=');
+                Token::from_str("=")
+            }
             '!' => {
                 if let Some('=') = self.buffer.peek() {
                     self.buffer.next();
@@ -86,40 +171,40 @@ impl Iterator for Tokenizer<'_> {
                     Token::from_str(">")
                 }
             }
-            '<' => {
-                if let Some('=') = self.buffer.peek() {
-                    self.buffer.next();
-                    Token::from_str("<=")
-                } else {
-                    Token::from_str("<")
-                }
-            }
-            '\'' => {
-                let mut token_qident = String::new();
-                token_qident.push(next);
-                loop {
-                    match self.buffer.next() {
-                        Some('\'') => {
-                            token_qident.push('\'');
-                            break;
-                        }
-                        Some(next) => token_qident.push(next),
-                        None => break,
-                    }
-                }
-                Token::from_str(&token_qident)
+            '<' => match self.buffer.next_if(|c| *c == '=' || *c == '>') {
+                Some('=') => Token::from_str("<="),
+                // `<>` is the same operator as `!=`
+                Some(_) => Token::from_str("!="),
+                None => Token::from_str("<"),
+            },
+            '\'' => self.read_quoted('\'').map(Token::QIdent),
+            // Blob literal: X'0A1B'
+            'x' | 'X' if self.buffer.peek() == Some(&'\'') => {
+                self.buffer.next();
+                self.read_quoted('\'')
+                    .and_then(|hex| decode_hex(&hex))
+                    .map(Token::Blob)
             }
+            // Quoted identifiers, identifiers are case insensitive even when quoted
+            '"' => self
+                .read_quoted('"')
+                .map(|ident| Token::Ident(ident.to_lowercase())),
+            '`' => self
+                .read_quoted('`')
+                .map(|ident| Token::Ident(ident.to_lowercase())),
+            '[' => self
+                .read_quoted(']')
+                .map(|ident| Token::Ident(ident.to_lowercase())),
+            // Number literals: 12, 2.5, .5, 1e-5, 0x1F
+            '0'..='9' => self.read_number(next),
+            '.' if self.buffer.peek().is_some_and(char::is_ascii_digit) => self.read_number(next),
             '.' => {
                 let mut command = ".".to_string();
-                loop {
-                    if let Some(next) = self.buffer.next() {
-                        command.push(next);
-                    } else {
-                        break;
-                    }
+                for next in self.buffer.by_ref() {
+                    command.push(next);
                 }
                 let command = command.trim();
-                Token::from_str(&command)
+                Token::from_str(command)
             }
             _ => {
                 let mut token_str = String::new();
@@ -130,10 +215,7 @@ impl Iterator for Tokenizer<'_> {
                     };
 
                     if is_stop_identifier(*peek) {
-                        if *peek == ' ' {
-                            self.buffer.next();
-                            self.trim_space();
-                        }
+                        self.trim_space();
                         break;
                     }
                     next = self.buffer.next().expect("We just peek before");
@@ -145,9 +227,25 @@ impl Iterator for Tokenizer<'_> {
     }
 }
 
-const STOP_CHARS: [char; 12] = [';', '(', ')', ',', ' ', '*', '=', '<', '!', '>', '+', '-'];
+const STOP_CHARS: [char; 17] = [
+    ';', '(', ')', ',', '*', '=', '<', '!', '>', '+', '-', '/', '%', '|', '\'', '"', '`',
+];
+
+// Bytes of the hexadecimal digits of a blob literal, two digits per byte
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Unrecognized token: X'{}'", hex));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
 
 fn is_stop_identifier(c: char) -> bool {
+    if c.is_whitespace() {
+        return true;
+    }
     for stop_char in STOP_CHARS.iter() {
         if c == *stop_char {
             return true;
@@ -297,6 +395,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_should_tokenize_reals() {
+        let tokenizer = Tokenizer::new("price >= 2.5 + 1e3-1e-5*2.5E+3/.5, 1., 0x1F");
+
+        let expected_tokens = [
+            Token::Ident("price".to_string()),
+            Token::GTEQ,
+            Token::Real(2.5),
+            Token::Plus,
+            Token::Real(1000.0),
+            Token::Minus,
+            Token::Real(1e-5),
+            Token::Star,
+            Token::Real(2500.0),
+            Token::Div,
+            Token::Real(0.5),
+            Token::Coma,
+            Token::Real(1.0),
+            Token::Coma,
+            Token::Num(31),
+        ];
+
+        let tokens = tokenizer.map(|token| token.unwrap()).collect::<Vec<_>>();
+        assert_eq!(tokens, expected_tokens);
+
+        for number in ["1e", "2.5e+", "12abc"] {
+            let mut tokenizer = Tokenizer::new(number);
+            let err = tokenizer.next().unwrap().unwrap_err();
+            assert!(
+                err.to_string().starts_with("Unrecognized token"),
+                "{}",
+                number
+            );
+        }
+    }
+
     #[test]
     fn it_should_tokenize_token_qident() {
         let tokenizer = Tokenizer::new("name = 'hello world'");
@@ -311,4 +445,61 @@ mod tests {
             assert_eq!(token.unwrap(), expected);
         }
     }
+
+    #[test]
+    fn it_should_skip_comments_and_new_lines() {
+        let tokenizer =
+            Tokenizer::new("SELECT -- comment\n\tname /* block\n comment */ FROM apples\n");
+
+        let expected_tokens = [
+            Token::Select,
+            Token::Ident("name".to_string()),
+            Token::From,
+            Token::Ident("apples".to_string()),
+        ];
+
+        let tokens = tokenizer.map(|token| token.unwrap()).collect::<Vec<_>>();
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn it_should_tokenize_quoted_identifiers() {
+        let tokenizer = Tokenizer::new("\"Size Range\", [line no], `code`, 'it''s'");
+
+        let expected_tokens = [
+            Token::Ident("size range".to_string()),
+            Token::Coma,
+            Token::Ident("line no".to_string()),
+            Token::Coma,
+            Token::Ident("code".to_string()),
+            Token::Coma,
+            Token::QIdent("it's".to_string()),
+        ];
+
+        let tokens = tokenizer.map(|token| token.unwrap()).collect::<Vec<_>>();
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn it_should_tokenize_comparison_aliases() {
+        let tokenizer = Tokenizer::new("a<>1 AND a==2 OR a IS NOT NULL");
+
+        let expected_tokens = [
+            Token::Ident("a".to_string()),
+            Token::NotEq,
+            Token::Num(1),
+            Token::And,
+            Token::Ident("a".to_string()),
+            Token::Equal,
+            Token::Num(2),
+            Token::Or,
+            Token::Ident("a".to_string()),
+            Token::Is,
+            Token::Not,
+            Token::Null,
+        ];
+
+        let tokens = tokenizer.map(|token| token.unwrap()).collect::<Vec<_>>();
+        assert_eq!(tokens, expected_tokens);
+    }
 }
@@ -1,94 +1,157 @@
-use anyhow::Result;
+use crate::executor::db_response::RType;
+use crate::parser::expr::{BinaryOperator, Expr};
 use std::fmt;
 
-use crate::{executor::db_response::RType, parser::token::Token};
-
-#[derive(Debug)]
-pub enum Operator {
-    Eq,
-    NotEq,
-    LT,
-    GT,
-    LTE,
-    GTE,
-}
-
-impl fmt::Display for Operator {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Operator::Eq => write!(f, "="),
-            Operator::NotEq => write!(f, "!="),
-            Operator::LT => write!(f, "<"),
-            Operator::GT => write!(f, ">"),
-            Operator::LTE => write!(f, "<="),
-            Operator::GTE => write!(f, ">="),
-        }
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Where {
-    left: Token,
-    operator: Token,
-    right: Token,
+    pub expr: Expr,
 }
 
 impl Where {
-    pub fn new(left: Token, operator: Token, right: Token) -> Result<Self> {
-        Ok(Self {
-            left,
-            operator,
-            right,
-        })
+    pub fn new(expr: Expr) -> Self {
+        Self { expr }
     }
 
-    pub fn get_identifier(&self) -> Option<&str> {
-        if let Token::Ident(ident) = &self.right {
-            return Some(ident);
-        };
-        if let Token::Ident(ident) = &self.left {
-            return Some(ident);
-        };
-        None
+    /// Returns the column, the operator and the value of every `column <op> value` condition
+    /// that must hold for a row to match: the clause itself or the operands of its top level ANDs.
+    /// When the value is on the left, the operator is flipped so it reads `column <op> value`.
+    pub fn get_comparisons(&self) -> Vec<(&str, BinaryOperator, RType)> {
+        self.get_conditions()
+            .into_iter()
+            .filter_map(|condition| {
+                let Expr::Binary {
+                    left,
+                    operator,
+                    right,
+                } = condition
+                else {
+                    return None;
+                };
+                if !operator.is_comparison() {
+                    return None;
+                }
+                match (left.as_ref(), right.as_ref()) {
+                    (Expr::Column(column), Expr::Literal(value)) => {
+                        Some((column.as_str(), *operator, value.clone()))
+                    }
+                    (Expr::Literal(value), Expr::Column(column)) => {
+                        Some((column.as_str(), operator.flip(), value.clone()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the column and the values of every `column IN (value, ...)` condition
+    /// that must hold for a row to match.
+    pub fn get_in_lists(&self) -> Vec<(&str, Vec<RType>)> {
+        self.get_conditions()
+            .into_iter()
+            .filter_map(|condition| {
+                let Expr::InList {
+                    expr,
+                    list,
+                    negated: false,
+                } = condition
+                else {
+                    return None;
+                };
+                let Expr::Column(column) = expr.as_ref() else {
+                    return None;
+                };
+                let values = list
+                    .iter()
+                    .map(|value| match value {
+                        Expr::Literal(value) => Some(value.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some((column.as_str(), values))
+            })
+            .collect()
     }
 
-    pub fn evaluate(&self, value: Option<&RType>) -> bool {
-        if let Some(value) = value {
-            let left: RType = self.right.into_rtype();
-            &left == value
-        } else {
-            self.left == self.right
-        }
+    // The clause itself or the operands of its top level ANDs
+    fn get_conditions(&self) -> Vec<&Expr> {
+        self.expr.get_conditions()
     }
 }
 
 impl fmt::Display for Where {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "WHERE {} {} {}", self.left, self.operator, self.right)
+        write!(f, "WHERE {}", self.expr)
     }
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
     #[test]
-    fn it_should_evaluate_none_1() {
-        let where_clause = Where::new(
-            Token::Num(5),
-            Token::Equal,
-            Token::QIdent("Hello".to_string()),
-        )
-        .unwrap();
-        let result = where_clause.evaluate(None);
-        assert_eq!(result, false)
+    fn it_should_flip_comparison_with_value_on_the_left() {
+        let where_clause = Where::new(Expr::binary(
+            Expr::Literal(RType::Num(5)),
+            BinaryOperator::LT,
+            Expr::Column("id".to_string()),
+        ));
+        assert_eq!(
+            where_clause.get_comparisons(),
+            vec![("id", BinaryOperator::GT, RType::Num(5))]
+        );
+    }
+
+    #[test]
+    fn it_should_not_plan_arithmetic_as_comparison() {
+        let where_clause = Where::new(Expr::binary(
+            Expr::Column("id".to_string()),
+            BinaryOperator::Add,
+            Expr::Literal(RType::Num(5)),
+        ));
+        assert!(where_clause.get_comparisons().is_empty());
     }
 
     #[test]
-    fn it_should_evaluate_none_2() {
-        let where_clause = Where::new(Token::Num(5), Token::Equal, Token::Num(1)).unwrap();
-        let result = where_clause.evaluate(None);
-        assert_eq!(result, true)
+    fn it_should_collect_comparisons_of_and_operands_only() {
+        let comparison = |column: &str, value| {
+            Expr::binary(
+                Expr::Column(column.to_string()),
+                BinaryOperator::Eq,
+                Expr::Literal(RType::Num(value)),
+            )
+        };
+        let where_clause = Where::new(Expr::binary(
+            Expr::binary(comparison("a", 1), BinaryOperator::And, comparison("b", 2)),
+            BinaryOperator::And,
+            Expr::binary(comparison("c", 3), BinaryOperator::Or, comparison("d", 4)),
+        ));
+        assert_eq!(
+            where_clause.get_comparisons(),
+            vec![
+                ("a", BinaryOperator::Eq, RType::Num(1)),
+                ("b", BinaryOperator::Eq, RType::Num(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_collect_in_lists_of_literals() {
+        let in_list = |column: &str, list| Expr::InList {
+            expr: Box::new(Expr::Column(column.to_string())),
+            list,
+            negated: false,
+        };
+        let where_clause = Where::new(Expr::binary(
+            in_list(
+                "a",
+                vec![Expr::Literal(RType::Num(1)), Expr::Literal(RType::Num(2))],
+            ),
+            BinaryOperator::And,
+            in_list("b", vec![Expr::Column("c".to_string())]),
+        ));
+        assert_eq!(
+            where_clause.get_in_lists(),
+            vec![("a", vec![RType::Num(1), RType::Num(2)])]
+        );
     }
 }
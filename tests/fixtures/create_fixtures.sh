@@ -0,0 +1,91 @@
+#!/bin/sh
+# Build the databases used by the tests. Requires sqlite3.
+cd "$(dirname "$0")"
+
+echo "Creating indexes.db: indexes that cannot be searched with BINARY comparisons"
+rm -f indexes.db
+sqlite3 indexes.db <<SQL
+PRAGMA page_size = 512;
+CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, n INT);
+WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 1200)
+INSERT INTO t (name, n)
+SELECT CASE i % 4 WHEN 0 THEN 'alpha' WHEN 1 THEN 'Alpha' WHEN 2 THEN 'beta' ELSE 'BETA' END, i % 20 + 2
+FROM seq;
+CREATE INDEX idx_name_nocase ON t (name COLLATE NOCASE);
+CREATE INDEX idx_n_desc ON t (n DESC);
+SQL
+
+echo "Creating rowids.db: tables read with the rowid pseudo-columns"
+rm -f rowids.db
+sqlite3 rowids.db <<SQL
+PRAGMA page_size = 512;
+CREATE TABLE items (name TEXT);
+WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 500)
+INSERT INTO items (rowid, name) SELECT i * 3, 'item' || i FROM seq;
+CREATE TABLE shadowed (rowid TEXT, oid INT);
+INSERT INTO shadowed VALUES ('first', 10), ('second', 20);
+CREATE TABLE keyed (k TEXT PRIMARY KEY, v INT) WITHOUT ROWID;
+INSERT INTO keyed VALUES ('a', 1);
+SQL
+
+echo "Creating utf16.db: text ordered in the UTF-16LE encoding"
+rm -f utf16.db
+sqlite3 utf16.db <<SQL
+PRAGMA encoding = 'UTF-16le';
+CREATE TABLE words (s TEXT, n INT);
+INSERT INTO words VALUES ('a', 1), ('b', 2), ('c', 3), ('Ā', 4), ('z', 5), ('B', 6);
+CREATE INDEX words_s ON words (s);
+SQL
+
+echo "Creating schema.db: a schema spanning several pages, with DDL stored in overflow pages"
+rm -f schema.db
+{
+    echo "PRAGMA page_size = 512;"
+    for i in $(seq 1 40); do
+        echo "CREATE TABLE table_$i (id INTEGER PRIMARY KEY, name TEXT);"
+        echo "INSERT INTO table_$i (name) VALUES ('row of table $i');"
+    done
+    columns=$(seq 1 60 | sed 's/.*/long_column_name_& TEXT/' | paste -sd, -)
+    echo "CREATE TABLE wide ($columns);"
+    echo "INSERT INTO wide (long_column_name_1, long_column_name_60) VALUES ('first', 'last');"
+} | sqlite3 schema.db
+
+echo "Creating unsupported.db: schema objects the parser does not support"
+rm -f unsupported.db
+sqlite3 unsupported.db <<SQL
+CREATE TABLE good (a INT);
+INSERT INTO good VALUES (1), (2);
+CREATE TABLE odd (like INT, glob INT);
+INSERT INTO odd VALUES (1, 2);
+CREATE VIEW plain AS SELECT a FROM good WHERE a = 2;
+CREATE VIEW cases AS SELECT CASE WHEN a > 1 THEN 'big' ELSE 'small' END AS size FROM good;
+CREATE VIEW unions AS SELECT a FROM good UNION SELECT a FROM good;
+CREATE VIEW casts AS SELECT CAST(a AS TEXT) AS t FROM good;
+CREATE VIEW nested AS SELECT * FROM (SELECT a FROM good);
+SQL
+
+echo "Creating collations.db: comparisons with the declared collation of columns"
+rm -f collations.db
+sqlite3 collations.db <<SQL
+CREATE TABLE keyed (x TEXT PRIMARY KEY COLLATE NOCASE, n INT) WITHOUT ROWID;
+INSERT INTO keyed VALUES ('FOO1', 1), ('FOO2', 2), ('FOO3', 3), ('FOO4', 4);
+CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT COLLATE NOCASE);
+INSERT INTO people (name) VALUES ('Alice'), ('ALICE'), ('alice'), ('Bob');
+CREATE INDEX people_name ON people (name COLLATE BINARY);
+CREATE TABLE pairs (a TEXT COLLATE NOCASE, b TEXT);
+INSERT INTO pairs VALUES ('abc', 'ABC'), ('bob', 'bob'), ('alice', 'x');
+CREATE INDEX pairs_b ON pairs (b);
+SQL
+
+echo "Creating joins.db: joins read through the plan of their first table"
+rm -f joins.db
+sqlite3 joins.db <<SQL
+PRAGMA page_size = 512;
+CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT, country TEXT);
+WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 300)
+INSERT INTO authors SELECT i, 'author' || i, CASE i % 3 WHEN 0 THEN 'FR' ELSE 'UK' END FROM seq;
+CREATE INDEX authors_country ON authors (country);
+CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INT, title TEXT);
+WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 600)
+INSERT INTO books SELECT i, (i + 1) / 2, 'book' || i FROM seq;
+SQL